@@ -346,11 +346,9 @@ impl DdsHeader {
                 (16, 0x0000FFFF, 0x00000000, 0x00000000, 0x00000000) => return R16_UNORM,
                 (16, 0x000000FF, 0x00000000, 0x00000000, 0x0000FF00) => return R8G8_UNORM,
                 _ => TextureFormat::Uncompressed(UncompressedFormat::UnsignedNormalized(
-                    ChannelFormat::BitMask(
+                    ChannelFormat::LuminanceBitMask(
                         self.rgb_bit_counts,
                         self.r_bit_mask,
-                        self.g_bit_mask,
-                        self.b_bit_mask,
                         self.a_bit_mask,
                     ),
                 )),
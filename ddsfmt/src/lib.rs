@@ -112,7 +112,10 @@ pub enum ChannelFormat {
     RG(usize, usize),
     R(usize),
     A(usize),
+    /// Legacy pixel format with a bit count followed by red, green, blue and alpha masks.
     BitMask(u32, u32, u32, u32, u32),
+    /// Legacy luminance pixel format with a bit count followed by luminance and alpha masks.
+    LuminanceBitMask(u32, u32, u32),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -1 +1,869 @@
+use crate::{
+    decode_block_at, decode_block_surface, encode_block_surface, BlockLayout, Blocks, DecodedBlock,
+    Decoder, DecoderResult, Encoder, EncoderInput, EncoderOptions, EncoderResult, Quality,
+    SurfaceDescriptor, TexelFormat,
+};
+use byteorder::{ByteOrder, LE};
+use ddsfmt::BlockCompressionType;
+use std::sync::OnceLock;
 
+pub(crate) const BC1_LAYOUT: BlockLayout = BlockLayout::new(4, 4, 8);
+
+/// Decoder for BC1 (DXT1) surfaces, producing RGBA8 texels.
+///
+/// Blocks whose first endpoint is less than or equal to the second use the 3-color mode, where the
+/// fourth palette entry decodes as transparent black. All other blocks are fully opaque.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc1Decoder {
+    format: BlockCompressionType,
+}
+
+impl Bc1Decoder {
+    pub fn new(format: BlockCompressionType) -> Bc1Decoder {
+        Bc1Decoder { format }
+    }
+
+    /// The compression type of the surface. sRGB surfaces decode to sRGB-encoded texels.
+    pub fn format(&self) -> BlockCompressionType {
+        self.format
+    }
+}
+
+impl Decoder for Bc1Decoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(bytes, surface, BC1_LAYOUT, 4, buffer, |block, texels| {
+            decode_color_block(block, true, texels)
+        })
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            BC1_LAYOUT,
+            TexelFormat::RGBA8,
+            |block, texels| decode_color_block(block, true, texels),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, BC1_LAYOUT)
+    }
+}
+
+/// Expands a packed 5:6:5 color to RGB8 by bit replication.
+pub(crate) fn unpack_565(color: u16) -> [u8; 3] {
+    let r = ((color >> 11) & 0x1F) as u8;
+    let g = ((color >> 5) & 0x3F) as u8;
+    let b = (color & 0x1F) as u8;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
+
+/// Builds the four-entry RGBA8 palette of a BC1-style color block.
+///
+/// When `allow_3_color` is false the block is always decoded in 4-color mode, as required for the
+/// color half of BC2 and BC3 blocks.
+pub(crate) fn color_palette(c0: u16, c1: u16, allow_3_color: bool) -> [[u8; 4]; 4] {
+    let [r0, g0, b0] = unpack_565(c0);
+    let [r1, g1, b1] = unpack_565(c1);
+    let third = |a: u8, b: u8| ((2 * a as u32 + b as u32 + 1) / 3) as u8;
+    let half = |a: u8, b: u8| (a as u32 + b as u32).div_ceil(2) as u8;
+
+    if c0 > c1 || !allow_3_color {
+        [
+            [r0, g0, b0, 255],
+            [r1, g1, b1, 255],
+            [third(r0, r1), third(g0, g1), third(b0, b1), 255],
+            [third(r1, r0), third(g1, g0), third(b1, b0), 255],
+        ]
+    } else {
+        [
+            [r0, g0, b0, 255],
+            [r1, g1, b1, 255],
+            [half(r0, r1), half(g0, g1), half(b0, b1), 255],
+            [0, 0, 0, 0],
+        ]
+    }
+}
+
+/// Decodes an 8-byte BC1-style color block into 16 row-major RGBA8 texels.
+pub(crate) fn decode_color_block(block: &[u8], allow_3_color: bool, texels: &mut [u8]) {
+    let palette = color_palette(
+        LE::read_u16(&block[0..2]),
+        LE::read_u16(&block[2..4]),
+        allow_3_color,
+    );
+    let indices = LE::read_u32(&block[4..8]);
+    for (i, texel) in texels.chunks_exact_mut(4).enumerate() {
+        texel.copy_from_slice(&palette[((indices >> (2 * i)) & 0x3) as usize]);
+    }
+}
+
+/// Encoder for BC1 (DXT1) surfaces.
+///
+/// [`Quality::Fast`] fits endpoints to the range of colors along their principal axis.
+/// [`Quality::Normal`] adds a cluster fit, testing every ordered assignment of colors to palette
+/// entries, and [`Quality::Slow`] iterates the cluster fit and refines the resulting endpoints.
+/// Blocks of a single color use endpoints chosen to reproduce that color as closely as possible.
+///
+/// Each block is encoded in both the 4-color and the 3-color mode, keeping whichever has the lower
+/// error. Texels with an alpha below the [`alpha_threshold`](Bc1Encoder::alpha_threshold) are
+/// encoded as transparent, which requires the 3-color mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc1Encoder {
+    format: BlockCompressionType,
+    alpha_threshold: u8,
+    three_color: bool,
+}
+
+impl Bc1Encoder {
+    pub fn new(format: BlockCompressionType) -> Bc1Encoder {
+        Bc1Encoder {
+            format,
+            alpha_threshold: 0,
+            three_color: true,
+        }
+    }
+
+    /// Sets the 8-bit alpha below which texels are punched through as transparent black, for
+    /// DXT1a content. The default of 0 encodes every texel as opaque.
+    pub fn alpha_threshold(mut self, alpha_threshold: u8) -> Bc1Encoder {
+        self.alpha_threshold = alpha_threshold;
+        self
+    }
+
+    /// Allows opaque blocks to use the 3-color mode when it has a lower error. Some older
+    /// hardware decodes 3-color blocks incorrectly, so this can be disabled.
+    pub fn three_color(mut self, three_color: bool) -> Bc1Encoder {
+        self.three_color = three_color;
+        self
+    }
+
+    /// The compression type of the surface. Texels are encoded as is for sRGB surfaces.
+    pub fn format(&self) -> BlockCompressionType {
+        self.format
+    }
+}
+
+impl Encoder for Bc1Encoder {
+    fn encode_to(
+        &self,
+        input: EncoderInput<'_>,
+        surface: SurfaceDescriptor,
+        options: EncoderOptions,
+        buffer: &mut Vec<u8>,
+    ) -> EncoderResult<()> {
+        encode_block_surface(input, surface, BC1_LAYOUT, buffer, |texels, block| {
+            let threshold = self.alpha_threshold as f32;
+            let transparent = texels
+                .iter()
+                .enumerate()
+                .filter(|(_, texel)| (texel[3] * 255.0).round() < threshold)
+                .fold(0, |mask, (i, _)| mask | 1 << i);
+            let mode = if transparent != 0 {
+                ColorMode::Transparent(transparent)
+            } else if self.three_color {
+                ColorMode::Any
+            } else {
+                ColorMode::FourColor
+            };
+            block.copy_from_slice(&encode_color_block(texels, mode, &options));
+        })
+    }
+}
+
+/// Palette modes allowed when encoding a BC1-style color block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ColorMode {
+    /// Either the 4-color or the 3-color mode of a BC1 block.
+    Any,
+    /// Only the 4-color mode of a BC1 block.
+    FourColor,
+    /// The 3-color mode of a BC1 block, with the texels set in the mask transparent.
+    Transparent(u16),
+    /// The color half of a BC2 or BC3 block, which always decodes in 4-color mode.
+    Opaque,
+}
+
+type Rgb = [f32; 3];
+
+/// Encodes 16 row-major texels into an 8-byte BC1-style color block.
+pub(crate) fn encode_color_block(
+    texels: &[[f32; 4]],
+    mode: ColorMode,
+    options: &EncoderOptions,
+) -> [u8; 8] {
+    let transparent = match mode {
+        ColorMode::Transparent(mask) => mask,
+        _ => 0,
+    };
+    let fit = ColorFit::new(texels, transparent, options);
+    if fit.count == 0 {
+        return [0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+    }
+    let mut best = Candidate {
+        error: f32::INFINITY,
+        block: [0; 8],
+    };
+    let three_color: &[bool] = match mode {
+        ColorMode::Any => &[false, true],
+        ColorMode::FourColor | ColorMode::Opaque => &[false],
+        ColorMode::Transparent(_) => &[true],
+    };
+    let allow_3_color = mode != ColorMode::Opaque;
+
+    if fit.points[..fit.count]
+        .iter()
+        .all(|point| *point == fit.points[0])
+    {
+        let color = fit.unscale(fit.points[0]);
+        for &three in three_color {
+            let (e0, e1) = single_color_endpoints(color, three);
+            best.consider(fit.evaluate(e0, e1, three, allow_3_color));
+        }
+        return best.block;
+    }
+
+    for &three in three_color {
+        let mut axis = fit.principal_axis();
+        let (e0, e1) = fit.range_fit(axis);
+        best.consider(fit.evaluate(e0, e1, three, allow_3_color));
+        if options.quality == Quality::Fast {
+            continue;
+        }
+
+        let iterations = if options.quality == Quality::Slow {
+            8
+        } else {
+            1
+        };
+        let mut best_error = f32::INFINITY;
+        for _ in 0..iterations {
+            let (e0, e1, error) = fit.cluster_fit(axis, three);
+            if error >= best_error {
+                break;
+            }
+            best_error = error;
+            best.consider(fit.evaluate(e0, e1, three, allow_3_color));
+            let (start, end) = (fit.scale_color(e0), fit.scale_color(e1));
+            axis = [end[0] - start[0], end[1] - start[1], end[2] - start[2]];
+            if axis == [0.0; 3] {
+                break;
+            }
+        }
+    }
+
+    if options.quality == Quality::Slow {
+        best = fit.refine(best, allow_3_color, mode == ColorMode::Any);
+    }
+    best.block
+}
+
+/// An encoded block and its error.
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    error: f32,
+    block: [u8; 8],
+}
+
+impl Candidate {
+    fn consider(&mut self, other: Candidate) {
+        if other.error < self.error {
+            *self = other;
+        }
+    }
+
+    fn endpoints(&self) -> (u16, u16) {
+        (
+            LE::read_u16(&self.block[0..2]),
+            LE::read_u16(&self.block[2..4]),
+        )
+    }
+}
+
+/// The opaque texels of a block, in a space where each channel is in `[0, 255]` scaled by the
+/// square root of its weight, so squared distances are weighted errors.
+struct ColorFit<'a> {
+    texels: &'a [[f32; 4]],
+    transparent: u16,
+    scale: Rgb,
+    points: [Rgb; 16],
+    count: usize,
+}
+
+impl<'a> ColorFit<'a> {
+    fn new(texels: &'a [[f32; 4]], transparent: u16, options: &EncoderOptions) -> ColorFit<'a> {
+        let weights = options.channel_weights;
+        let scale = [
+            weights[0].max(0.0).sqrt(),
+            weights[1].max(0.0).sqrt(),
+            weights[2].max(0.0).sqrt(),
+        ];
+        let mut fit = ColorFit {
+            texels,
+            transparent,
+            scale,
+            points: [[0.0; 3]; 16],
+            count: 0,
+        };
+        for (i, texel) in texels.iter().enumerate() {
+            if transparent & (1 << i) == 0 {
+                fit.points[fit.count] = fit.scale([texel[0], texel[1], texel[2]]);
+                fit.count += 1;
+            }
+        }
+        fit
+    }
+
+    /// Scales a normalized color into the fitting space.
+    fn scale(&self, color: Rgb) -> Rgb {
+        let mut point = [0.0; 3];
+        for c in 0..3 {
+            point[c] = color[c].clamp(0.0, 1.0) * 255.0 * self.scale[c];
+        }
+        point
+    }
+
+    /// Converts a point of the fitting space back to a color in `[0, 255]`.
+    fn unscale(&self, point: Rgb) -> Rgb {
+        let mut color = [0.0; 3];
+        for c in 0..3 {
+            if self.scale[c] > 0.0 {
+                color[c] = (point[c] / self.scale[c]).clamp(0.0, 255.0);
+            }
+        }
+        color
+    }
+
+    /// Scales a packed 5:6:5 color into the fitting space.
+    fn scale_color(&self, color: u16) -> Rgb {
+        let [r, g, b] = unpack_565(color);
+        [
+            r as f32 * self.scale[0],
+            g as f32 * self.scale[1],
+            b as f32 * self.scale[2],
+        ]
+    }
+
+    /// Rounds a point of the fitting space to the nearest packed 5:6:5 color.
+    fn quantize(&self, point: Rgb) -> u16 {
+        let [r, g, b] = self.unscale(point);
+        let r = (r * 31.0 / 255.0).round() as u16;
+        let g = (g * 63.0 / 255.0).round() as u16;
+        let b = (b * 31.0 / 255.0).round() as u16;
+        (r << 11) | (g << 5) | b
+    }
+
+    fn points(&self) -> &[Rgb] {
+        &self.points[..self.count]
+    }
+
+    /// Direction of greatest variance of the points, by power iteration on their covariance.
+    fn principal_axis(&self) -> Rgb {
+        let n = self.count as f32;
+        let mut centroid = [0.0; 3];
+        for point in self.points() {
+            for c in 0..3 {
+                centroid[c] += point[c] / n;
+            }
+        }
+        let mut covariance = [[0.0; 3]; 3];
+        for point in self.points() {
+            let d = [
+                point[0] - centroid[0],
+                point[1] - centroid[1],
+                point[2] - centroid[2],
+            ];
+            for (row, &di) in covariance.iter_mut().zip(d.iter()) {
+                for (value, &dj) in row.iter_mut().zip(d.iter()) {
+                    *value += di * dj;
+                }
+            }
+        }
+
+        let mut axis = [1.0; 3];
+        for _ in 0..8 {
+            let mut next = [0.0; 3];
+            for (value, row) in next.iter_mut().zip(covariance.iter()) {
+                *value = row[0] * axis[0] + row[1] * axis[1] + row[2] * axis[2];
+            }
+            let max = next.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+            if max == 0.0 {
+                break;
+            }
+            axis = [next[0] / max, next[1] / max, next[2] / max];
+        }
+        axis
+    }
+
+    fn project(point: Rgb, axis: Rgb) -> f32 {
+        point[0] * axis[0] + point[1] * axis[1] + point[2] * axis[2]
+    }
+
+    /// Uses the points with the lowest and highest projections onto `axis` as endpoints.
+    fn range_fit(&self, axis: Rgb) -> (u16, u16) {
+        let (mut min, mut max) = (self.points[0], self.points[0]);
+        for &point in self.points() {
+            if Self::project(point, axis) < Self::project(min, axis) {
+                min = point;
+            }
+            if Self::project(point, axis) > Self::project(max, axis) {
+                max = point;
+            }
+        }
+        (self.quantize(max), self.quantize(min))
+    }
+
+    /// Finds the endpoints minimizing the least squares error over every way of splitting the
+    /// points, ordered along `axis`, into consecutive clusters for each palette entry. Returns
+    /// the quantized endpoints and their error, not counting the constant error of the points.
+    fn cluster_fit(&self, axis: Rgb, three_color: bool) -> (u16, u16, f32) {
+        let mut order: Vec<Rgb> = self.points().to_vec();
+        order.sort_by(|a, b| Self::project(*b, axis).total_cmp(&Self::project(*a, axis)));
+        let n = order.len();
+        let mut prefix = vec![[0.0; 3]; n + 1];
+        for (i, point) in order.iter().enumerate() {
+            for c in 0..3 {
+                prefix[i + 1][c] = prefix[i][c] + point[c];
+            }
+        }
+        let sum = |from: usize, to: usize| {
+            let mut sum = [0.0; 3];
+            for c in 0..3 {
+                sum[c] = prefix[to][c] - prefix[from][c];
+            }
+            sum
+        };
+
+        // Weight of the first endpoint in each cluster
+        let weights: &[f32] = if three_color {
+            &[1.0, 0.5, 0.0]
+        } else {
+            &[1.0, 2.0 / 3.0, 1.0 / 3.0, 0.0]
+        };
+        let mut best = (0, 0, f32::INFINITY);
+        let mut consider = |bounds: &[usize]| {
+            let (mut alpha2, mut beta2, mut alpha_beta) = (0.0, 0.0, 0.0);
+            let (mut alpha_x, mut beta_x) = ([0.0; 3], [0.0; 3]);
+            for (cluster, &alpha) in weights.iter().enumerate() {
+                let (from, to) = (bounds[cluster], bounds[cluster + 1]);
+                let count = (to - from) as f32;
+                let beta = 1.0 - alpha;
+                alpha2 += alpha * alpha * count;
+                beta2 += beta * beta * count;
+                alpha_beta += alpha * beta * count;
+                let x = sum(from, to);
+                for c in 0..3 {
+                    alpha_x[c] += alpha * x[c];
+                    beta_x[c] += beta * x[c];
+                }
+            }
+            let det = alpha2 * beta2 - alpha_beta * alpha_beta;
+            if det.abs() < 1e-6 {
+                return;
+            }
+            let (mut a, mut b) = ([0.0; 3], [0.0; 3]);
+            for c in 0..3 {
+                a[c] = (alpha_x[c] * beta2 - beta_x[c] * alpha_beta) / det;
+                b[c] = (beta_x[c] * alpha2 - alpha_x[c] * alpha_beta) / det;
+            }
+            let error = |a: Rgb, b: Rgb| {
+                let mut error = 0.0;
+                for c in 0..3 {
+                    error +=
+                        a[c] * a[c] * alpha2 + b[c] * b[c] * beta2 + 2.0 * a[c] * b[c] * alpha_beta
+                            - 2.0 * (a[c] * alpha_x[c] + b[c] * beta_x[c]);
+                }
+                error
+            };
+            // The unquantized endpoints are optimal, so their error bounds the quantized error
+            if error(a, b) >= best.2 {
+                return;
+            }
+            let (e0, e1) = (self.quantize(a), self.quantize(b));
+            let error = error(self.scale_color(e0), self.scale_color(e1));
+            if error < best.2 {
+                best = (e0, e1, error);
+            }
+        };
+
+        if three_color {
+            for i in 0..=n {
+                for j in i..=n {
+                    consider(&[0, i, j, n]);
+                }
+            }
+        } else {
+            for i in 0..=n {
+                for j in i..=n {
+                    for k in j..=n {
+                        consider(&[0, i, j, k, n]);
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Encodes the block with endpoints `e0` and `e1`, ordered for the requested mode, and
+    /// measures its error against the texels.
+    fn evaluate(&self, e0: u16, e1: u16, three_color: bool, allow_3_color: bool) -> Candidate {
+        let (e0, e1) = match (allow_3_color, three_color) {
+            (false, _) => (e0, e1),
+            (true, true) => (e0.min(e1), e0.max(e1)),
+            (true, false) if e0 == e1 => {
+                // Equal endpoints select the 3-color mode, so move one of them a step of blue
+                // away, whichever way the channel has room for
+                let mut best = Candidate {
+                    error: f32::INFINITY,
+                    block: [0; 8],
+                };
+                if e0 & 0x1F < 0x1F {
+                    best.consider(self.evaluate(e0 + 1, e1, false, true));
+                }
+                if e1 & 0x1F > 0 {
+                    best.consider(self.evaluate(e0, e1 - 1, false, true));
+                }
+                return best;
+            }
+            (true, false) => (e0.max(e1), e0.min(e1)),
+        };
+        let palette = color_palette(e0, e1, allow_3_color);
+        let mut indices = 0u32;
+        let mut error = 0.0;
+        for (i, texel) in self.texels.iter().enumerate() {
+            let index = if self.transparent & (1 << i) != 0 {
+                3
+            } else {
+                let point = self.scale([texel[0], texel[1], texel[2]]);
+                let mut best = (0, f32::INFINITY);
+                for (index, entry) in palette.iter().enumerate().filter(|(_, e)| e[3] == 255) {
+                    let mut distance = 0.0;
+                    for c in 0..3 {
+                        let d = entry[c] as f32 * self.scale[c] - point[c];
+                        distance += d * d;
+                    }
+                    if distance < best.1 {
+                        best = (index, distance);
+                    }
+                }
+                error += best.1;
+                best.0
+            };
+            indices |= (index as u32) << (2 * i);
+        }
+
+        let mut block = [0; 8];
+        LE::write_u16(&mut block[0..2], e0);
+        LE::write_u16(&mut block[2..4], e1);
+        LE::write_u32(&mut block[4..8], indices);
+        Candidate { error, block }
+    }
+
+    /// Nudges each endpoint channel up or down by one step while doing so lowers the error.
+    fn refine(&self, mut best: Candidate, allow_3_color: bool, any_mode: bool) -> Candidate {
+        let steps = [1 << 11, 1 << 5, 1];
+        let masks = [0x1F << 11, 0x3F << 5, 0x1F];
+        for _ in 0..8 {
+            let (e0, e1) = best.endpoints();
+            let three_color = allow_3_color && e0 <= e1;
+            let mut improved = false;
+            for endpoint in 0..2 {
+                for (&step, &mask) in steps.iter().zip(masks.iter()) {
+                    let color = if endpoint == 0 { e0 } else { e1 };
+                    let candidates = [
+                        (color & mask).checked_add(step).filter(|v| v & !mask == 0),
+                        (color & mask).checked_sub(step),
+                    ];
+                    for channel in candidates.iter().flatten() {
+                        let color = (color & !mask) | channel;
+                        let (c0, c1) = if endpoint == 0 {
+                            (color, e1)
+                        } else {
+                            (e0, color)
+                        };
+                        let modes = [three_color, !three_color];
+                        for &three in &modes[..1 + any_mode as usize] {
+                            let candidate = self.evaluate(c0, c1, three, allow_3_color);
+                            if candidate.error < best.error {
+                                best = candidate;
+                                improved = true;
+                            }
+                        }
+                    }
+                }
+            }
+            if !improved {
+                break;
+            }
+        }
+        best
+    }
+}
+
+/// Endpoints whose first interpolated palette entry best reproduces a single color in `[0, 255]`.
+fn single_color_endpoints(color: Rgb, three_color: bool) -> (u16, u16) {
+    let tables = SINGLE_COLOR_TABLES.get_or_init(SingleColorTables::new);
+    let mode = three_color as usize;
+    let [r, g, b] = color.map(|c| c.round() as usize);
+    let (r0, r1) = tables.five[mode][r];
+    let (g0, g1) = tables.six[mode][g];
+    let (b0, b1) = tables.five[mode][b];
+    let pack = |r: u8, g: u8, b: u8| ((r as u16) << 11) | ((g as u16) << 5) | b as u16;
+    (pack(r0, g0, b0), pack(r1, g1, b1))
+}
+
+/// For each 8-bit value, the 5 and 6-bit endpoints whose interpolation at the third palette
+/// entry is closest to it, in 4-color and 3-color mode.
+struct SingleColorTables {
+    five: [[(u8, u8); 256]; 2],
+    six: [[(u8, u8); 256]; 2],
+}
+
+static SINGLE_COLOR_TABLES: OnceLock<SingleColorTables> = OnceLock::new();
+
+impl SingleColorTables {
+    fn new() -> SingleColorTables {
+        let build = |bits: u32, three_color: bool| {
+            let max = (1u32 << bits) - 1;
+            let expand = |q: u32| (q << (8 - bits)) | (q >> (2 * bits - 8));
+            let mut table = [(0, 0); 256];
+            for (value, entry) in table.iter_mut().enumerate() {
+                let mut best = u32::MAX;
+                for q0 in 0..=max {
+                    for q1 in 0..=max {
+                        let (a, b) = (expand(q0), expand(q1));
+                        let interpolated = if three_color {
+                            (a + b).div_ceil(2)
+                        } else {
+                            (2 * a + b + 1) / 3
+                        };
+                        let error = (interpolated as i32 - value as i32).unsigned_abs();
+                        if error < best {
+                            best = error;
+                            *entry = (q0 as u8, q1 as u8);
+                        }
+                    }
+                }
+            }
+            table
+        };
+        SingleColorTables {
+            five: [build(5, false), build(5, true)],
+            six: [build(6, false), build(6, true)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EncoderInput, Quality};
+
+    fn decode(blocks: &[u8], surface: SurfaceDescriptor) -> Vec<u8> {
+        Bc1Decoder::new(BlockCompressionType::UnsignedNormalized)
+            .decode(blocks, surface)
+            .unwrap()
+    }
+
+    #[test]
+    fn decodes_four_color_block() {
+        // Red and blue endpoints, with indices 0, 1, 2, 3 in every row
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0xE4, 0xE4, 0xE4, 0xE4];
+        let row = [
+            [255, 0, 0, 255],
+            [0, 0, 255, 255],
+            [170, 0, 85, 255],
+            [85, 0, 170, 255],
+        ];
+        let expected: Vec<u8> = row.iter().flatten().copied().cycle().take(64).collect();
+        assert_eq!(decode(&block, SurfaceDescriptor::new(4, 4)), expected);
+    }
+
+    #[test]
+    fn decodes_three_color_block() {
+        // Swapping the endpoints selects the 3-color mode, with index 3 transparent black
+        let block = [0x1F, 0x00, 0x00, 0xF8, 0xE4, 0xE4, 0xE4, 0xE4];
+        let row = [
+            [0, 0, 255, 255],
+            [255, 0, 0, 255],
+            [128, 0, 128, 255],
+            [0, 0, 0, 0],
+        ];
+        let expected: Vec<u8> = row.iter().flatten().copied().cycle().take(64).collect();
+        assert_eq!(decode(&block, SurfaceDescriptor::new(4, 4)), expected);
+    }
+
+    #[test]
+    fn decodes_equal_endpoints_as_three_color() {
+        // Rows select indices 3, 0, 2 and 1, the first being transparent black
+        let block = [0xE0, 0x07, 0xE0, 0x07, 0xFF, 0x00, 0xAA, 0x55];
+        let decoded = decode(&block, SurfaceDescriptor::new(4, 4));
+        for (row, texels) in decoded.chunks_exact(16).enumerate() {
+            let expected = if row == 0 {
+                [0, 0, 0, 0]
+            } else {
+                [0, 255, 0, 255]
+            };
+            assert!(texels.chunks_exact(4).all(|t| t == expected), "row {}", row);
+        }
+    }
+
+    #[test]
+    fn crops_partial_blocks() {
+        // Four solid blocks, red, green, blue and white, covering a 5x5 surface
+        let mut blocks = Vec::new();
+        for color in [0xF800u16, 0x07E0, 0x001F, 0xFFFF] {
+            blocks.extend_from_slice(&color.to_le_bytes());
+            blocks.extend_from_slice(&[0; 6]);
+        }
+        let decoded = decode(&blocks, SurfaceDescriptor::new(5, 5));
+        assert_eq!(decoded.len(), 5 * 5 * 4);
+        for (i, texel) in decoded.chunks_exact(4).enumerate() {
+            let expected = match (i % 5 == 4, i / 5 == 4) {
+                (false, false) => [255, 0, 0, 255],
+                (true, false) => [0, 255, 0, 255],
+                (false, true) => [0, 0, 255, 255],
+                (true, true) => [255, 255, 255, 255],
+            };
+            assert_eq!(texel, expected, "texel {}", i);
+        }
+    }
+
+    fn encode(encoder: Bc1Encoder, rgba: &[u8], quality: Quality) -> Vec<u8> {
+        encoder
+            .encode(
+                EncoderInput::Rgba8(rgba),
+                SurfaceDescriptor::new(4, 4),
+                EncoderOptions::new(quality),
+            )
+            .unwrap()
+    }
+
+    /// A 16x16 gradient with deterministic noise, as RGBA8.
+    fn test_image() -> Vec<u8> {
+        let mut state = 0x2545_F491u32;
+        let mut rgba = Vec::new();
+        for y in 0..16 {
+            for x in 0..16 {
+                let mut noise = || {
+                    state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    (state >> 24) as i32 % 12 - 6
+                };
+                let r = x * 15 + noise();
+                let g = x * 10 + y * 3 + noise();
+                let b = 255 - x * 12 - y + noise();
+                let a = (x + y) * 8;
+                for value in [r, g, b, a] {
+                    rgba.push(value.clamp(0, 255) as u8);
+                }
+            }
+        }
+        rgba
+    }
+
+    /// Squared errors of the RGB channels of each block of a 16x16 image.
+    fn block_errors(original: &[u8], decoded: &[u8]) -> Vec<u32> {
+        let mut errors = vec![0; 16];
+        for (i, (a, b)) in original
+            .chunks_exact(4)
+            .zip(decoded.chunks_exact(4))
+            .enumerate()
+        {
+            let block = (i / 64) * 4 + (i % 16) / 4;
+            for c in 0..3 {
+                let d = a[c] as i32 - b[c] as i32;
+                errors[block] += (d * d) as u32;
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn cluster_fit_is_no_worse_than_range_fit() {
+        let rgba = test_image();
+        let surface = SurfaceDescriptor::new(16, 16);
+        let decoder = Bc1Decoder::new(BlockCompressionType::UnsignedNormalized);
+        let encoder = Bc1Encoder::new(BlockCompressionType::UnsignedNormalized);
+        let errors = |quality| {
+            let options = EncoderOptions::new(quality);
+            let blocks = encoder
+                .encode(EncoderInput::Rgba8(&rgba), surface, options)
+                .unwrap();
+            block_errors(&rgba, &decoder.decode(&blocks, surface).unwrap())
+        };
+        let (range, cluster) = (errors(Quality::Fast), errors(Quality::Normal));
+        for (block, (range, cluster)) in range.iter().zip(cluster.iter()).enumerate() {
+            assert!(cluster <= range, "block {}: {} > {}", block, cluster, range);
+        }
+        let rmse = |errors: &[u32]| (errors.iter().sum::<u32>() as f32 / 768.0).sqrt();
+        assert!(rmse(&range) < 6.5, "range fit rmse {}", rmse(&range));
+        assert!(rmse(&cluster) < 5.0, "cluster fit rmse {}", rmse(&cluster));
+    }
+
+    #[test]
+    fn texels_below_alpha_threshold_are_transparent() {
+        let rgba = test_image();
+        let surface = SurfaceDescriptor::new(16, 16);
+        let decoder = Bc1Decoder::new(BlockCompressionType::UnsignedNormalized);
+        for &threshold in &[0, 1, 100, 255] {
+            let encoder = Bc1Encoder::new(BlockCompressionType::UnsignedNormalized)
+                .alpha_threshold(threshold);
+            let blocks = encoder
+                .encode(
+                    EncoderInput::Rgba8(&rgba),
+                    surface,
+                    EncoderOptions::default(),
+                )
+                .unwrap();
+            let decoded = decoder.decode(&blocks, surface).unwrap();
+            for (original, texel) in rgba.chunks_exact(4).zip(decoded.chunks_exact(4)) {
+                if original[3] < threshold {
+                    assert_eq!(texel, [0, 0, 0, 0]);
+                } else {
+                    assert_eq!(texel[3], 255);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn four_color_blocks_keep_endpoints_ordered() {
+        let encoder = Bc1Encoder::new(BlockCompressionType::UnsignedNormalized).three_color(false);
+        for color in (0..=255).step_by(15) {
+            for noise in 0..3 {
+                // Nearly solid blocks, including exact 5:6:5 colors at both ends of each channel
+                let mut rgba = Vec::new();
+                for i in 0..16 {
+                    let offset = if i % 5 == 0 { noise } else { 0 };
+                    let value = (color as u8).saturating_add(offset);
+                    rgba.extend_from_slice(&[value, 255 - value, value / 2, 255]);
+                }
+                for &quality in &[Quality::Fast, Quality::Normal, Quality::Slow] {
+                    let block = encode(encoder, &rgba, quality);
+                    let (c0, c1) = (LE::read_u16(&block[0..2]), LE::read_u16(&block[2..4]));
+                    assert!(c0 > c1, "{:04x} {:04x} for {} {}", c0, c1, color, noise);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::{
+    bc1::{decode_color_block, encode_color_block, ColorMode},
+    decode_block_at, decode_block_surface, encode_block_surface, unpremultiply_rgba8, BlockLayout,
+    Blocks, DecodedBlock, Decoder, DecoderResult, Encoder, EncoderInput, EncoderOptions,
+    EncoderResult, SurfaceDescriptor, TexelFormat,
+};
+use byteorder::{ByteOrder, LE};
+use ddsfmt::BlockCompressionType;
+
+pub(crate) const BC2_LAYOUT: BlockLayout = BlockLayout::new(4, 4, 16);
+
+/// Decoder for BC2 (DXT2 & DXT3) surfaces, producing RGBA8 texels.
+///
+/// Each block stores 4 bits of explicit alpha per texel, followed by a BC1 color block that is
+/// always decoded in 4-color mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc2Decoder {
+    format: BlockCompressionType,
+    unpremultiply: bool,
+}
+
+impl Bc2Decoder {
+    pub fn new(format: BlockCompressionType) -> Bc2Decoder {
+        Bc2Decoder {
+            format,
+            unpremultiply: false,
+        }
+    }
+
+    /// Treats the color channels as premultiplied by alpha, as in DXT2 content, and converts
+    /// decoded texels back to straight alpha.
+    pub fn unpremultiply(mut self, unpremultiply: bool) -> Bc2Decoder {
+        self.unpremultiply = unpremultiply;
+        self
+    }
+
+    /// The compression type of the surface. sRGB surfaces decode to sRGB-encoded texels.
+    pub fn format(&self) -> BlockCompressionType {
+        self.format
+    }
+
+    fn decode_block(&self, block: &[u8], texels: &mut [u8]) {
+        decode_color_block(&block[8..16], false, texels);
+        decode_explicit_alpha_block(&block[0..8], texels);
+        if self.unpremultiply {
+            unpremultiply_rgba8(texels);
+        }
+    }
+}
+
+impl Decoder for Bc2Decoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(bytes, surface, BC2_LAYOUT, 4, buffer, |block, texels| {
+            self.decode_block(block, texels)
+        })
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            BC2_LAYOUT,
+            TexelFormat::RGBA8,
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, BC2_LAYOUT)
+    }
+}
+
+/// Encoder for BC2 (DXT3) surfaces.
+///
+/// Alpha is rounded to 4 bits per texel, optionally [dithered](Bc2Encoder::dither). Color blocks
+/// are encoded as in [`Bc1Encoder`] without the 3-color mode.
+///
+/// [`Bc1Encoder`]: crate::Bc1Encoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc2Encoder {
+    format: BlockCompressionType,
+    dither: bool,
+}
+
+impl Bc2Encoder {
+    pub fn new(format: BlockCompressionType) -> Bc2Encoder {
+        Bc2Encoder {
+            format,
+            dither: false,
+        }
+    }
+
+    /// Diffuses the rounding error of each alpha value to its neighbours within the block, which
+    /// trades banding in smooth gradients for noise.
+    pub fn dither(mut self, dither: bool) -> Bc2Encoder {
+        self.dither = dither;
+        self
+    }
+
+    /// The compression type of the surface. Texels are encoded as is for sRGB surfaces.
+    pub fn format(&self) -> BlockCompressionType {
+        self.format
+    }
+}
+
+impl Encoder for Bc2Encoder {
+    fn encode_to(
+        &self,
+        input: EncoderInput<'_>,
+        surface: SurfaceDescriptor,
+        options: EncoderOptions,
+        buffer: &mut Vec<u8>,
+    ) -> EncoderResult<()> {
+        encode_block_surface(input, surface, BC2_LAYOUT, buffer, |texels, block| {
+            let mut alpha = [0.0; 16];
+            for (value, texel) in alpha.iter_mut().zip(texels) {
+                *value = texel[3];
+            }
+            block[0..8].copy_from_slice(&encode_explicit_alpha_block(&alpha, self.dither));
+            block[8..16].copy_from_slice(&encode_color_block(texels, ColorMode::Opaque, &options));
+        })
+    }
+}
+
+/// Decodes an 8-byte block of explicit 4-bit alpha into the alpha channel of 16 RGBA8 texels.
+pub(crate) fn decode_explicit_alpha_block(block: &[u8], texels: &mut [u8]) {
+    let alpha = LE::read_u64(block);
+    for (i, texel) in texels.chunks_exact_mut(4).enumerate() {
+        texel[3] = ((alpha >> (4 * i)) & 0xF) as u8 * 17;
+    }
+}
+
+/// Encodes 16 row-major alpha values in `[0, 1]` into an 8-byte block of explicit 4-bit alpha.
+///
+/// Dithering uses Floyd-Steinberg error diffusion, dropping the error that would leave the block.
+pub(crate) fn encode_explicit_alpha_block(alpha: &[f32; 16], dither: bool) -> [u8; 8] {
+    let mut values = alpha.map(|a| a.clamp(0.0, 1.0) * 15.0);
+    let mut bits = 0;
+    for i in 0..16 {
+        let quantized = values[i].round().clamp(0.0, 15.0);
+        bits |= (quantized as u64) << (4 * i);
+        if dither {
+            let error = values[i] - quantized;
+            let (x, y) = (i % 4, i / 4);
+            for &(dx, dy, weight) in &[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)] {
+                let (x, y) = (x as isize + dx, y as isize + dy);
+                if (0..4).contains(&x) && y < 4 {
+                    values[(y * 4 + x) as usize] += error * weight / 16.0;
+                }
+            }
+        }
+    }
+    let mut block = [0; 8];
+    LE::write_u64(&mut block, bits);
+    block
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_explicit_alpha() {
+        // Alpha levels 0 to 15 in order, and a color block that would be 3-color in BC1
+        let block = [
+            0x10, 0x32, 0x54, 0x76, 0x98, 0xBA, 0xDC, 0xFE, 0x00, 0x00, 0xFF, 0xFF, 0xE4, 0xE4,
+            0xE4, 0xE4,
+        ];
+        let decoded = Bc2Decoder::new(BlockCompressionType::UnsignedNormalized)
+            .decode(&block, SurfaceDescriptor::new(4, 4))
+            .unwrap();
+        let colors = [[0, 0, 0], [255, 255, 255], [85, 85, 85], [170, 170, 170]];
+        for (i, texel) in decoded.chunks_exact(4).enumerate() {
+            assert_eq!(texel[..3], colors[i % 4], "texel {}", i);
+            assert_eq!(texel[3], i as u8 * 17, "texel {}", i);
+        }
+    }
+
+    #[test]
+    fn unpremultiplies_color() {
+        // Alpha 0, 8 and 15 over a gray of 132, 130, 132
+        let block = [
+            0x80, 0x0F, 0, 0, 0, 0, 0, 0, 0x10, 0x84, 0x10, 0x84, 0, 0, 0, 0,
+        ];
+        let decoder = Bc2Decoder::new(BlockCompressionType::UnsignedNormalized);
+        let surface = SurfaceDescriptor::new(4, 4);
+        let plain = decoder.decode(&block, surface).unwrap();
+        assert_eq!(
+            plain[..12],
+            [132, 130, 132, 0, 132, 130, 132, 136, 132, 130, 132, 255]
+        );
+        let unpremultiplied = decoder.unpremultiply(true).decode(&block, surface).unwrap();
+        assert_eq!(
+            unpremultiplied[..12],
+            [132, 130, 132, 0, 248, 244, 248, 136, 132, 130, 132, 255]
+        );
+    }
+
+    fn levels(block: [u8; 8]) -> [u64; 16] {
+        let bits = LE::read_u64(&block);
+        let mut levels = [0; 16];
+        for (i, level) in levels.iter_mut().enumerate() {
+            *level = (bits >> (4 * i)) & 0xF;
+        }
+        levels
+    }
+
+    #[test]
+    fn explicit_alpha_rounds_to_nearest_level() {
+        let mut alpha = [0.0; 16];
+        for (i, value) in alpha.iter_mut().enumerate() {
+            // Just short of halfway between levels i and i + 1 still rounds down
+            *value = (i as f32 + if i % 2 == 0 { 0.49 } else { 0.0 }) / 15.0;
+        }
+        let expected: Vec<u64> = (0..16).collect();
+        assert_eq!(
+            levels(encode_explicit_alpha_block(&alpha, false)).to_vec(),
+            expected
+        );
+    }
+
+    #[test]
+    fn round_trips_alpha_gradient() {
+        let mut rgba = Vec::new();
+        for y in 0..8 {
+            for x in 0..8 {
+                rgba.extend_from_slice(&[x * 32, y * 32, 128, (x * 8 + y) * 4 + 1]);
+            }
+        }
+        let surface = SurfaceDescriptor::new(8, 8);
+        let decoder = Bc2Decoder::new(BlockCompressionType::UnsignedNormalized);
+        for &dither in &[false, true] {
+            let encoder = Bc2Encoder::new(BlockCompressionType::UnsignedNormalized).dither(dither);
+            let blocks = encoder
+                .encode(
+                    EncoderInput::Rgba8(&rgba),
+                    surface,
+                    EncoderOptions::default(),
+                )
+                .unwrap();
+            let decoded = decoder.decode(&blocks, surface).unwrap();
+            let alpha = |texels: &[u8], block: usize| -> Vec<i32> {
+                let (bx, by) = (block % 2 * 4, block / 2 * 4);
+                (0..16)
+                    .map(|i| texels[((by + i / 4) * 8 + bx + i % 4) * 4 + 3] as i32)
+                    .collect()
+            };
+            for block in 0..4 {
+                let (original, decoded) = (alpha(&rgba, block), alpha(&decoded, block));
+                let errors: Vec<i32> = original.iter().zip(&decoded).map(|(a, b)| b - a).collect();
+                if dither {
+                    // Diffusion trades per-texel error for a block average close to the input
+                    let mean = errors.iter().sum::<i32>() as f32 / 16.0;
+                    assert!(
+                        mean.abs() <= 2.0,
+                        "dither {} block {} mean {}",
+                        dither,
+                        block,
+                        mean
+                    );
+                    assert!(errors.iter().all(|e| e.abs() <= 17));
+                } else {
+                    assert!(errors.iter().all(|e| e.abs() <= 8), "block {}", block);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn explicit_alpha_exact_levels_ignore_dither() {
+        let alpha: [f32; 16] = std::array::from_fn(|i| (i * 7 % 16) as f32 / 15.0);
+        assert_eq!(
+            encode_explicit_alpha_block(&alpha, true),
+            encode_explicit_alpha_block(&alpha, false)
+        );
+    }
+
+    #[test]
+    fn explicit_alpha_dither_preserves_average() {
+        // 0.5 lies halfway between levels 7 and 8
+        let alpha = [0.5; 16];
+        let plain = levels(encode_explicit_alpha_block(&alpha, false));
+        assert_eq!(plain, [8; 16]);
+        let dithered = levels(encode_explicit_alpha_block(&alpha, true));
+        assert!(dithered.iter().all(|&level| level == 7 || level == 8));
+        let sum: u64 = dithered.iter().sum();
+        assert!((116..=124).contains(&sum), "sum {}", sum);
+    }
+}
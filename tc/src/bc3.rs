@@ -0,0 +1,249 @@
+use crate::{
+    bc1::{decode_color_block, encode_color_block, ColorMode},
+    bc4::fit_interpolated_block,
+    decode_block_at, decode_block_surface, encode_block_surface, unpremultiply_rgba8, BlockLayout,
+    Blocks, DecodedBlock, Decoder, DecoderResult, Encoder, EncoderInput, EncoderOptions,
+    EncoderResult, Quality, SurfaceDescriptor, TexelFormat,
+};
+use byteorder::{ByteOrder, LE};
+use ddsfmt::BlockCompressionType;
+
+pub(crate) const BC3_LAYOUT: BlockLayout = BlockLayout::new(4, 4, 16);
+
+/// Decoder for BC3 (DXT4 & DXT5) surfaces, producing RGBA8 texels.
+///
+/// Each block stores an interpolated alpha block followed by a BC1 color block that is always
+/// decoded in 4-color mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc3Decoder {
+    format: BlockCompressionType,
+    unpremultiply: bool,
+}
+
+impl Bc3Decoder {
+    pub fn new(format: BlockCompressionType) -> Bc3Decoder {
+        Bc3Decoder {
+            format,
+            unpremultiply: false,
+        }
+    }
+
+    /// Treats the color channels as premultiplied by alpha, as in DXT4 content, and converts
+    /// decoded texels back to straight alpha.
+    pub fn unpremultiply(mut self, unpremultiply: bool) -> Bc3Decoder {
+        self.unpremultiply = unpremultiply;
+        self
+    }
+
+    /// The compression type of the surface. sRGB surfaces decode to sRGB-encoded texels.
+    pub fn format(&self) -> BlockCompressionType {
+        self.format
+    }
+
+    fn decode_block(&self, block: &[u8], texels: &mut [u8]) {
+        decode_color_block(&block[8..16], false, texels);
+        let palette = alpha_palette(block[0], block[1]);
+        let indices = alpha_indices(block);
+        for (i, texel) in texels.chunks_exact_mut(4).enumerate() {
+            texel[3] = palette[((indices >> (3 * i)) & 0x7) as usize];
+        }
+        if self.unpremultiply {
+            unpremultiply_rgba8(texels);
+        }
+    }
+}
+
+impl Decoder for Bc3Decoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(bytes, surface, BC3_LAYOUT, 4, buffer, |block, texels| {
+            self.decode_block(block, texels)
+        })
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            BC3_LAYOUT,
+            TexelFormat::RGBA8,
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, BC3_LAYOUT)
+    }
+}
+
+/// Encoder for BC3 (DXT5) surfaces.
+///
+/// Alpha blocks are encoded in both the interpolated and the extremes mode, searching endpoints
+/// around the range of the alpha values more widely at higher [`Quality`] levels, up to every pair
+/// at [`Quality::Slow`], and keeping whichever mode has the lower error. Color blocks are encoded
+/// as in [`Bc1Encoder`] without the 3-color mode.
+///
+/// [`Bc1Encoder`]: crate::Bc1Encoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc3Encoder {
+    format: BlockCompressionType,
+}
+
+impl Bc3Encoder {
+    pub fn new(format: BlockCompressionType) -> Bc3Encoder {
+        Bc3Encoder { format }
+    }
+
+    /// The compression type of the surface. Texels are encoded as is for sRGB surfaces.
+    pub fn format(&self) -> BlockCompressionType {
+        self.format
+    }
+}
+
+impl Encoder for Bc3Encoder {
+    fn encode_to(
+        &self,
+        input: EncoderInput<'_>,
+        surface: SurfaceDescriptor,
+        options: EncoderOptions,
+        buffer: &mut Vec<u8>,
+    ) -> EncoderResult<()> {
+        encode_block_surface(input, surface, BC3_LAYOUT, buffer, |texels, block| {
+            let mut alpha = [0.0; 16];
+            for (value, texel) in alpha.iter_mut().zip(texels) {
+                *value = texel[3];
+            }
+            block[0..8].copy_from_slice(&encode_alpha_block(&alpha, options.quality));
+            block[8..16].copy_from_slice(&encode_color_block(texels, ColorMode::Opaque, &options));
+        })
+    }
+}
+
+/// Builds the eight-entry palette of an interpolated alpha block.
+///
+/// When `a0 > a1` the block interpolates six values between the endpoints, otherwise it
+/// interpolates four values and adds the extremes 0 and 255.
+pub(crate) fn alpha_palette(a0: u8, a1: u8) -> [u8; 8] {
+    let (a0, a1) = (a0 as u32, a1 as u32);
+    let mut palette = [a0 as u8, a1 as u8, 0, 0, 0, 0, 0, 255];
+    if a0 > a1 {
+        for (i, value) in palette[2..8].iter_mut().enumerate() {
+            let i = i as u32 + 1;
+            *value = (((7 - i) * a0 + i * a1 + 3) / 7) as u8;
+        }
+    } else {
+        for (i, value) in palette[2..6].iter_mut().enumerate() {
+            let i = i as u32 + 1;
+            *value = (((5 - i) * a0 + i * a1 + 2) / 5) as u8;
+        }
+    }
+    palette
+}
+
+/// Reads the 48 bits of 3-bit texel indices following the two endpoints of an interpolated alpha
+/// block.
+pub(crate) fn alpha_indices(block: &[u8]) -> u64 {
+    LE::read_u48(&block[2..8])
+}
+
+/// Encodes 16 row-major alpha values in `[0, 1]` into an interpolated alpha block.
+pub(crate) fn encode_alpha_block(alpha: &[f32; 16], quality: Quality) -> [u8; 8] {
+    let values = alpha.map(|a| a.clamp(0.0, 1.0) * 255.0);
+    fit_interpolated_block(&values, 0..=255, quality, |a0, a1| {
+        alpha_palette(a0 as u8, a1 as u8).map(f32::from)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes an alpha block over solid white, with indices 0 to 7 in order, twice.
+    fn decode_alpha(a0: u8, a1: u8) -> Vec<u8> {
+        let block = [
+            a0, a1, 0x88, 0xC6, 0xFA, 0x88, 0xC6, 0xFA, 0xFF, 0xFF, 0x00, 0x00, 0, 0, 0, 0,
+        ];
+        let decoded = Bc3Decoder::new(BlockCompressionType::UnsignedNormalized)
+            .decode(&block, SurfaceDescriptor::new(4, 4))
+            .unwrap();
+        assert!(decoded.chunks_exact(4).all(|t| t[..3] == [255, 255, 255]));
+        decoded.chunks_exact(4).map(|t| t[3]).collect()
+    }
+
+    #[test]
+    fn decodes_eight_value_alpha() {
+        let palette = [200, 60, 180, 160, 140, 120, 100, 80];
+        assert_eq!(decode_alpha(200, 60), palette.repeat(2));
+    }
+
+    #[test]
+    fn decodes_six_value_alpha() {
+        let palette = [60, 200, 88, 116, 144, 172, 0, 255];
+        assert_eq!(decode_alpha(60, 200), palette.repeat(2));
+        // Equal endpoints also select the 6-value mode
+        let palette = [90, 90, 90, 90, 90, 90, 0, 255];
+        assert_eq!(decode_alpha(90, 90), palette.repeat(2));
+    }
+
+    /// Encodes a block of alpha values through the encoder and decodes it again, returning the
+    /// alpha endpoints and the decoded alpha.
+    fn round_trip(alpha: [u8; 16], quality: Quality) -> ((u8, u8), Vec<u8>) {
+        let rgba: Vec<u8> = alpha.iter().flat_map(|&a| [64, 128, 192, a]).collect();
+        let surface = SurfaceDescriptor::new(4, 4);
+        let block = Bc3Encoder::new(BlockCompressionType::UnsignedNormalized)
+            .encode(
+                EncoderInput::Rgba8(&rgba),
+                surface,
+                EncoderOptions::new(quality),
+            )
+            .unwrap();
+        let decoded = Bc3Decoder::new(BlockCompressionType::UnsignedNormalized)
+            .decode(&block, surface)
+            .unwrap();
+        (
+            (block[0], block[1]),
+            decoded.chunks_exact(4).map(|t| t[3]).collect(),
+        )
+    }
+
+    #[test]
+    fn exact_extremes_use_six_value_mode() {
+        let alpha: [u8; 16] = std::array::from_fn(|i| [0, 255, 100, 120][i % 4]);
+        for &quality in &[Quality::Fast, Quality::Normal, Quality::Slow] {
+            let ((a0, a1), decoded) = round_trip(alpha, quality);
+            assert!(a0 <= a1, "{} {} at {:?}", a0, a1, quality);
+            assert_eq!(decoded, alpha.to_vec(), "{:?}", quality);
+        }
+    }
+
+    #[test]
+    fn gradients_use_eight_value_mode() {
+        let alpha: [u8; 16] = std::array::from_fn(|i| 50 + i as u8 * 10);
+        for &quality in &[Quality::Fast, Quality::Normal, Quality::Slow] {
+            let ((a0, a1), decoded) = round_trip(alpha, quality);
+            assert!(a0 > a1, "{} {} at {:?}", a0, a1, quality);
+            let max_error = alpha
+                .iter()
+                .zip(&decoded)
+                .map(|(&a, &b)| (a as i32 - b as i32).abs())
+                .max()
+                .unwrap();
+            // Half the spacing of six interpolated steps across the 150 wide range
+            assert!(max_error <= 11, "error {} at {:?}", max_error, quality);
+        }
+    }
+}
@@ -0,0 +1,564 @@
+use crate::{
+    bc3::alpha_indices, decode_block_at, decode_block_surface, encode_block_surface, BlockLayout,
+    Blocks, ChannelType, DecodedBlock, Decoder, DecoderResult, Encoder, EncoderInput,
+    EncoderOptions, EncoderResult, Quality, SurfaceDescriptor, TexelFormat,
+};
+use byteorder::{ByteOrder, LE};
+use ddsfmt::SignedCompressionType;
+use std::ops::RangeInclusive;
+
+pub(crate) const BC4_LAYOUT: BlockLayout = BlockLayout::new(4, 4, 8);
+
+/// Decoder for BC4 (ATI1) single-channel surfaces, producing red channel texels.
+///
+/// Texels are written as [`ChannelType::Norm8`] by default. Signed surfaces decode to SNORM
+/// values, with both -127 and -128 mapping to -1.0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc4Decoder {
+    format: SignedCompressionType,
+    channel_type: ChannelType,
+}
+
+impl Bc4Decoder {
+    pub fn new(format: SignedCompressionType) -> Bc4Decoder {
+        Bc4Decoder {
+            format,
+            channel_type: ChannelType::Norm8,
+        }
+    }
+
+    /// Sets the storage of decoded texels.
+    pub fn channel_type(mut self, channel_type: ChannelType) -> Bc4Decoder {
+        self.channel_type = channel_type;
+        self
+    }
+
+    /// The compression type of the surface. Typeless surfaces decode as unsigned.
+    pub fn format(&self) -> SignedCompressionType {
+        self.format
+    }
+
+    fn texel_format(&self) -> TexelFormat {
+        let signed = self.format == SignedCompressionType::SignedNormalized;
+        TexelFormat::new(1, self.channel_type, signed)
+    }
+
+    fn decode_block(&self, block: &[u8], texels: &mut [u8]) {
+        let signed = self.format == SignedCompressionType::SignedNormalized;
+        let size = self.channel_type.size();
+        let values = decode_channel_block(block, signed);
+        for (value, texel) in values.iter().zip(texels.chunks_exact_mut(size)) {
+            self.channel_type.write(*value, signed, texel);
+        }
+    }
+}
+
+impl Decoder for Bc4Decoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        let size = self.texel_format().size();
+        decode_block_surface(bytes, surface, BC4_LAYOUT, size, buffer, |block, texels| {
+            self.decode_block(block, texels)
+        })
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            BC4_LAYOUT,
+            self.texel_format(),
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, BC4_LAYOUT)
+    }
+}
+
+/// Encoder for BC4 (ATI1) single-channel surfaces, encoding the red channel of its input.
+///
+/// Values keep the full precision of 16-bit and float input. Endpoints are searched in both block
+/// modes over a window around the range of the values that grows with the [`Quality`], and refined
+/// by least squares at [`Quality::Normal`] and above. [`Quality::Slow`] searches every pair of
+/// endpoints, finding the block with the lowest error.
+///
+/// Signed surfaces encode values in `[-1, 1]` to SNORM blocks, while unsigned and typeless
+/// surfaces clamp values to `[0, 1]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc4Encoder {
+    format: SignedCompressionType,
+}
+
+impl Bc4Encoder {
+    pub fn new(format: SignedCompressionType) -> Bc4Encoder {
+        Bc4Encoder { format }
+    }
+
+    /// The compression type of the surface. Typeless surfaces encode as unsigned.
+    pub fn format(&self) -> SignedCompressionType {
+        self.format
+    }
+}
+
+impl Encoder for Bc4Encoder {
+    fn encode_to(
+        &self,
+        input: EncoderInput<'_>,
+        surface: SurfaceDescriptor,
+        options: EncoderOptions,
+        buffer: &mut Vec<u8>,
+    ) -> EncoderResult<()> {
+        let signed = self.format == SignedCompressionType::SignedNormalized;
+        encode_block_surface(input, surface, BC4_LAYOUT, buffer, |texels, block| {
+            let mut values = [0.0; 16];
+            for (value, texel) in values.iter_mut().zip(texels) {
+                *value = texel[0];
+            }
+            block.copy_from_slice(&encode_channel_block(&values, signed, options.quality));
+        })
+    }
+}
+
+/// Builds the eight-entry palette of a BC4 block as normalized values.
+///
+/// Signed endpoints of -128 are clamped to -127 before interpolation, while the choice between
+/// the 8-value and 6-value modes compares the raw endpoints.
+pub(crate) fn channel_palette(e0: u8, e1: u8, signed: bool) -> [f32; 8] {
+    let (v0, v1, scale, min, max, six_value) = if signed {
+        let (s0, s1) = (e0 as i8, e1 as i8);
+        (
+            s0.max(-127) as i32,
+            s1.max(-127) as i32,
+            127.0,
+            -127,
+            127,
+            s0 <= s1,
+        )
+    } else {
+        (e0 as i32, e1 as i32, 255.0, 0, 255, e0 <= e1)
+    };
+
+    let mut palette = [0.0; 8];
+    palette[0] = v0 as f32 / scale;
+    palette[1] = v1 as f32 / scale;
+    if six_value {
+        for (i, value) in palette[2..6].iter_mut().enumerate() {
+            let i = i as i32 + 1;
+            *value = ((5 - i) * v0 + i * v1) as f32 / (5.0 * scale);
+        }
+        palette[6] = min as f32 / scale;
+        palette[7] = max as f32 / scale;
+    } else {
+        for (i, value) in palette[2..8].iter_mut().enumerate() {
+            let i = i as i32 + 1;
+            *value = ((7 - i) * v0 + i * v1) as f32 / (7.0 * scale);
+        }
+    }
+    palette
+}
+
+/// Decodes an 8-byte BC4 block into 16 row-major normalized values.
+pub(crate) fn decode_channel_block(block: &[u8], signed: bool) -> [f32; 16] {
+    let palette = channel_palette(block[0], block[1], signed);
+    let indices = alpha_indices(block);
+    let mut values = [0.0; 16];
+    for (i, value) in values.iter_mut().enumerate() {
+        *value = palette[((indices >> (3 * i)) & 0x7) as usize];
+    }
+    values
+}
+
+/// Encodes 16 row-major normalized values into an 8-byte BC4 block.
+pub(crate) fn encode_channel_block(values: &[f32; 16], signed: bool, quality: Quality) -> [u8; 8] {
+    if signed {
+        let values = values.map(|v| v.clamp(-1.0, 1.0) * 127.0);
+        fit_interpolated_block(&values, -127..=127, quality, |e0, e1| {
+            channel_palette(e0 as u8, e1 as u8, true).map(|v| v * 127.0)
+        })
+    } else {
+        let values = values.map(|v| v.clamp(0.0, 1.0) * 255.0);
+        fit_interpolated_block(&values, 0..=255, quality, |e0, e1| {
+            channel_palette(e0 as u8, e1 as u8, false).map(|v| v * 255.0)
+        })
+    }
+}
+
+/// Encodes 16 row-major values into an interpolated block, as used by BC3 alpha and BC4.
+///
+/// Values and the `palette` of a pair of endpoints are in endpoint units, with endpoints ranging
+/// over `levels`, whose bounds are the extremes added by the 6-value mode. Both modes are searched
+/// with endpoints within a radius of the range of the values that grows with the quality, fitting
+/// the 6-value mode to the values it can't represent exactly. Higher qualities then refine the
+/// best endpoints by least squares, and [`Quality::Slow`] searches every pair of endpoints in both
+/// modes.
+pub(crate) fn fit_interpolated_block<P>(
+    values: &[f32; 16],
+    levels: RangeInclusive<i32>,
+    quality: Quality,
+    palette: P,
+) -> [u8; 8]
+where
+    P: Fn(i32, i32) -> [f32; 8],
+{
+    let (lo, hi) = (*levels.start(), *levels.end());
+    let (radius, refinements) = match quality {
+        Quality::Fast => (0, 0),
+        Quality::Normal | Quality::Slow => (4, 1),
+    };
+    let range = |values: &mut dyn Iterator<Item = f32>| {
+        values.fold(None, |range, value| match range {
+            None => Some((value, value)),
+            Some((min, max)) => Some((f32::min(min, value), f32::max(max, value))),
+        })
+    };
+    let window = |value: f32| {
+        (value.floor() as i32 - radius).max(lo)..=(value.ceil() as i32 + radius).min(hi)
+    };
+    let consider = |best: &mut InterpolatedCandidate, e0: i32, e1: i32| {
+        let palette = palette(e0, e1);
+        let mut error = 0.0;
+        let mut indices = 0;
+        for (i, value) in values.iter().enumerate() {
+            let (index, value_error) = palette
+                .iter()
+                .map(|entry| (entry - value).powi(2))
+                .enumerate()
+                .fold((0, f32::INFINITY), |best, (index, error)| {
+                    if error < best.1 {
+                        (index, error)
+                    } else {
+                        best
+                    }
+                });
+            error += value_error;
+            if error >= best.error {
+                return;
+            }
+            indices |= (index as u64) << (3 * i);
+        }
+        *best = InterpolatedCandidate {
+            error,
+            endpoints: (e0, e1),
+            indices,
+        };
+    };
+
+    let mut best = InterpolatedCandidate {
+        error: f32::INFINITY,
+        endpoints: (lo, lo),
+        indices: 0,
+    };
+    // 8-value mode, with e0 > e1.
+    if let Some((min, max)) = range(&mut values.iter().copied()) {
+        for e0 in window(max) {
+            for e1 in window(min).filter(|e1| *e1 < e0) {
+                consider(&mut best, e0, e1);
+            }
+        }
+    }
+    // 6-value mode, with e0 <= e1.
+    let mut inner = values
+        .iter()
+        .copied()
+        .filter(|v| *v > lo as f32 + 0.5 && *v < hi as f32 - 0.5);
+    let (min, max) = range(&mut inner).unwrap_or((lo as f32, lo as f32));
+    for e0 in window(min) {
+        for e1 in window(max).filter(|e1| *e1 >= e0) {
+            consider(&mut best, e0, e1);
+        }
+    }
+
+    for _ in 0..refinements {
+        let error = best.error;
+        if let Some((e0, e1)) = best.least_squares_endpoints(values) {
+            let (e0, e1) = (e0.round() as i32, e1.round() as i32);
+            for d0 in -1..=1 {
+                for d1 in -1..=1 {
+                    consider(&mut best, (e0 + d0).clamp(lo, hi), (e1 + d1).clamp(lo, hi));
+                }
+            }
+        }
+        if best.error >= error {
+            break;
+        }
+    }
+
+    if quality == Quality::Slow {
+        // Search every pair, bounding the error of each by that of the values outside the range
+        // of the palette. The bounds grow as the range shrinks, ending each loop once they reach
+        // the best error so far.
+        let outside = |bound: &dyn Fn(f32, i32) -> f32| -> Vec<f32> {
+            (lo..=hi)
+                .map(|e| values.iter().map(|&v| bound(v, e)).sum())
+                .collect()
+        };
+        let sq = |d: f32| d.max(0.0).powi(2);
+        let (lof, hif) = (lo as f32, hi as f32);
+        let above = outside(&|v, e| sq(v - e as f32));
+        let below = outside(&|v, e| sq(e as f32 - v));
+        let below_extremes = outside(&|v, e| sq(e as f32 - v).min(sq(v - lof)));
+        let above_extremes = outside(&|v, e| sq(v - e as f32).min(sq(hif - v)));
+        let at = |bounds: &[f32], e: i32| bounds[(e - lo) as usize];
+
+        // 8-value mode, with e0 > e1.
+        for e0 in (lo + 1..=hi).rev() {
+            if at(&above, e0) >= best.error {
+                break;
+            }
+            for e1 in lo..e0 {
+                if at(&above, e0) + at(&below, e1) >= best.error {
+                    break;
+                }
+                consider(&mut best, e0, e1);
+            }
+        }
+        // 6-value mode, with e0 <= e1, whose extremes bound the error of values outside.
+        for e0 in lo..=hi {
+            if at(&below_extremes, e0) >= best.error {
+                break;
+            }
+            for e1 in (e0..=hi).rev() {
+                if at(&below_extremes, e0) + at(&above_extremes, e1) >= best.error {
+                    break;
+                }
+                consider(&mut best, e0, e1);
+            }
+        }
+    }
+
+    let (e0, e1) = best.endpoints;
+    let mut block = [e0 as u8, e1 as u8, 0, 0, 0, 0, 0, 0];
+    LE::write_u48(&mut block[2..8], best.indices);
+    block
+}
+
+struct InterpolatedCandidate {
+    error: f32,
+    endpoints: (i32, i32),
+    indices: u64,
+}
+
+impl InterpolatedCandidate {
+    /// Solves for the endpoints minimizing the squared error of the values with the candidate's
+    /// indices, ignoring the fixed extremes of the 6-value mode.
+    fn least_squares_endpoints(&self, values: &[f32; 16]) -> Option<(f32, f32)> {
+        let six_value = self.endpoints.0 <= self.endpoints.1;
+        let (mut aa, mut ab, mut bb, mut av, mut bv) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (i, value) in values.iter().enumerate() {
+            let t = match ((self.indices >> (3 * i)) & 0x7, six_value) {
+                (0, _) => 0.0,
+                (1, _) => 1.0,
+                (6 | 7, true) => continue,
+                (index, true) => (index - 1) as f32 / 5.0,
+                (index, false) => (index - 1) as f32 / 7.0,
+            };
+            let (a, b) = (1.0 - t, t);
+            aa += a * a;
+            ab += a * b;
+            bb += b * b;
+            av += a * value;
+            bv += b * value;
+        }
+        let det = aa * bb - ab * ab;
+        if det.abs() < 1e-6 {
+            return None;
+        }
+        Some(((bb * av - ab * bv) / det, (aa * bv - ab * av) / det))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes a block with indices 0 to 7 in order, twice, returning the first eight values in
+    /// endpoint units.
+    fn decode_palette(e0: u8, e1: u8, signed: bool) -> Vec<f32> {
+        let (format, scale) = if signed {
+            (SignedCompressionType::SignedNormalized, 127.0)
+        } else {
+            (SignedCompressionType::UnsignedNormalized, 255.0)
+        };
+        let block = [e0, e1, 0x88, 0xC6, 0xFA, 0x88, 0xC6, 0xFA];
+        let decoded = Bc4Decoder::new(format)
+            .channel_type(ChannelType::Float32)
+            .decode(&block, SurfaceDescriptor::new(4, 4))
+            .unwrap();
+        let values: Vec<f32> = decoded
+            .chunks_exact(4)
+            .map(|v| f32::from_ne_bytes([v[0], v[1], v[2], v[3]]) * scale)
+            .collect();
+        assert_eq!(values[..8], values[8..]);
+        values[..8].to_vec()
+    }
+
+    fn assert_palette(e0: u8, e1: u8, signed: bool, expected: &[f32]) {
+        let palette = decode_palette(e0, e1, signed);
+        for (value, expected) in palette.iter().zip(expected) {
+            assert!(
+                (value - expected).abs() < 1e-3,
+                "{:?} for {:02x} {:02x}",
+                palette,
+                e0,
+                e1
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_unsigned_modes() {
+        let eight_value: Vec<f32> = [255.0, 0.0]
+            .iter()
+            .copied()
+            .chain((1..7).rev().map(|i| 255.0 * i as f32 / 7.0))
+            .collect();
+        assert_palette(0xFF, 0x00, false, &eight_value);
+        assert_palette(
+            0x00,
+            0xFF,
+            false,
+            &[0.0, 255.0, 51.0, 102.0, 153.0, 204.0, 0.0, 255.0],
+        );
+    }
+
+    #[test]
+    fn decodes_signed_modes() {
+        let eight_value = |i: f32| (127.0 * (7.0 - i) - 127.0 * i) / 7.0;
+        assert_palette(
+            0x7F,
+            0x81,
+            true,
+            &[
+                127.0,
+                -127.0,
+                eight_value(1.0),
+                eight_value(2.0),
+                eight_value(3.0),
+                eight_value(4.0),
+                eight_value(5.0),
+                eight_value(6.0),
+            ],
+        );
+        // 1 and -1 are in 8-value order when signed, but in 6-value order when unsigned
+        assert_palette(
+            0x01,
+            0xFF,
+            true,
+            &[
+                1.0,
+                -1.0,
+                5.0 / 7.0,
+                3.0 / 7.0,
+                1.0 / 7.0,
+                -1.0 / 7.0,
+                -3.0 / 7.0,
+                -5.0 / 7.0,
+            ],
+        );
+        assert_palette(
+            0x81,
+            0x7F,
+            true,
+            &[-127.0, 127.0, -76.2, -25.4, 25.4, 76.2, -127.0, 127.0],
+        );
+    }
+
+    #[test]
+    fn signed_minus_128_decodes_as_minus_127() {
+        // The raw endpoints pick the mode: -128 < -127 selects the 6-value mode with its extremes,
+        // while -127 > -128 selects the 8-value mode between two values of -1
+        assert_palette(
+            0x80,
+            0x81,
+            true,
+            &[
+                -127.0, -127.0, -127.0, -127.0, -127.0, -127.0, -127.0, 127.0,
+            ],
+        );
+        assert_palette(0x81, 0x80, true, &[-127.0; 8]);
+        assert_palette(
+            0x80,
+            0x80,
+            true,
+            &[
+                -127.0, -127.0, -127.0, -127.0, -127.0, -127.0, -127.0, 127.0,
+            ],
+        );
+    }
+
+    /// Squared error of values in endpoint units against the palette of a pair of endpoints.
+    fn palette_error(values: &[f32; 16], e0: u8, e1: u8, signed: bool, scale: f32) -> f32 {
+        let palette = channel_palette(e0, e1, signed).map(|v| v * scale);
+        values
+            .iter()
+            .map(|v| {
+                palette
+                    .iter()
+                    .map(|entry| (entry - v).powi(2))
+                    .fold(f32::INFINITY, f32::min)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn slow_quality_matches_brute_force() {
+        let mut state = 0x9E37_79B9u32;
+        let mut random = || {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 24) as f32
+        };
+        let mut blocks: Vec<[f32; 16]> = vec![
+            std::array::from_fn(|i| i as f32 / 15.0),
+            std::array::from_fn(|i| [0.0, 1.0, 0.4, 0.47][i % 4]),
+            std::array::from_fn(|i| 0.3 + (i % 3) as f32 * 0.013),
+            std::array::from_fn(|i| if i < 8 { 0.1 } else { 0.85 }),
+        ];
+        for _ in 0..4 {
+            blocks.push(std::array::from_fn(|_| random()));
+        }
+
+        for &signed in &[false, true] {
+            let (scale, levels) = if signed {
+                (127.0, -127..=127)
+            } else {
+                (255.0, 0..=255)
+            };
+            for block in &blocks {
+                let values = block.map(|v| if signed { v * 2.0 - 1.0 } else { v });
+                let scaled = values.map(|v| v * scale);
+                let encoded = encode_channel_block(&values, signed, Quality::Slow);
+                let error = palette_error(&scaled, encoded[0], encoded[1], signed, scale);
+                let mut best = f32::INFINITY;
+                for e0 in levels.clone() {
+                    for e1 in levels.clone() {
+                        let error = palette_error(&scaled, e0 as u8, e1 as u8, signed, scale);
+                        best = best.min(error);
+                    }
+                }
+                assert!(
+                    error <= best + 1e-3,
+                    "{} > {} for {:?}",
+                    error,
+                    best,
+                    values
+                );
+            }
+        }
+    }
+}
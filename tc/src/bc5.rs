@@ -0,0 +1,295 @@
+use crate::{
+    bc4::{decode_channel_block, encode_channel_block},
+    decode_block_at, decode_block_surface, encode_block_surface, BlockLayout, Blocks, ChannelType,
+    DecodedBlock, Decoder, DecoderResult, Encoder, EncoderInput, EncoderOptions, EncoderResult,
+    SurfaceDescriptor, TexelFormat,
+};
+use ddsfmt::SignedCompressionType;
+
+pub(crate) const BC5_LAYOUT: BlockLayout = BlockLayout::new(4, 4, 16);
+
+/// Decoder for BC5 (ATI2) dual-channel surfaces, producing red and green channel texels.
+///
+/// Each block is a pair of BC4 blocks, one per channel. Texels are written as
+/// [`ChannelType::Norm8`] by default.
+///
+/// For tangent-space normal maps, [`reconstruct_z`](Bc5Decoder::reconstruct_z) adds a blue
+/// channel computed as `sqrt(1 - x² - y²)`. Unsigned surfaces are treated as normals mapped from
+/// `[-1, 1]` to `[0, 1]`, and the reconstructed channel uses the same mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc5Decoder {
+    format: SignedCompressionType,
+    channel_type: ChannelType,
+    reconstruct_z: bool,
+}
+
+impl Bc5Decoder {
+    pub fn new(format: SignedCompressionType) -> Bc5Decoder {
+        Bc5Decoder {
+            format,
+            channel_type: ChannelType::Norm8,
+            reconstruct_z: false,
+        }
+    }
+
+    /// Sets the storage of each decoded channel.
+    pub fn channel_type(mut self, channel_type: ChannelType) -> Bc5Decoder {
+        self.channel_type = channel_type;
+        self
+    }
+
+    /// Rebuilds the blue channel of a tangent-space normal map, producing three channels per
+    /// texel instead of two.
+    pub fn reconstruct_z(mut self, reconstruct_z: bool) -> Bc5Decoder {
+        self.reconstruct_z = reconstruct_z;
+        self
+    }
+
+    /// The compression type of the surface. Typeless surfaces decode as unsigned.
+    pub fn format(&self) -> SignedCompressionType {
+        self.format
+    }
+
+    fn texel_format(&self) -> TexelFormat {
+        let signed = self.format == SignedCompressionType::SignedNormalized;
+        let channels = if self.reconstruct_z { 3 } else { 2 };
+        TexelFormat::new(channels, self.channel_type, signed)
+    }
+
+    fn decode_block(&self, block: &[u8], texels: &mut [u8]) {
+        let format = self.texel_format();
+        let size = self.channel_type.size();
+        let red = decode_channel_block(&block[0..8], format.signed);
+        let green = decode_channel_block(&block[8..16], format.signed);
+        let texels = texels.chunks_exact_mut(format.size());
+        for ((r, g), texel) in red.iter().zip(green.iter()).zip(texels) {
+            self.channel_type
+                .write(*r, format.signed, &mut texel[..size]);
+            self.channel_type
+                .write(*g, format.signed, &mut texel[size..2 * size]);
+            if self.reconstruct_z {
+                let b = reconstruct_z(*r, *g, format.signed);
+                self.channel_type
+                    .write(b, format.signed, &mut texel[2 * size..]);
+            }
+        }
+    }
+}
+
+impl Decoder for Bc5Decoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(
+            bytes,
+            surface,
+            BC5_LAYOUT,
+            self.texel_format().size(),
+            buffer,
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            BC5_LAYOUT,
+            self.texel_format(),
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, BC5_LAYOUT)
+    }
+}
+
+/// Encoder for BC5 (ATI2) dual-channel surfaces, encoding the red and green channels of its input.
+///
+/// Each channel is encoded independently as in [`Bc4Encoder`], keeping the full precision of
+/// 16-bit and float input. For tangent-space normal maps, only the X and Y components should be
+/// provided, mapped to `[0, 1]` for unsigned surfaces.
+///
+/// [`Bc4Encoder`]: crate::Bc4Encoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc5Encoder {
+    format: SignedCompressionType,
+}
+
+impl Bc5Encoder {
+    pub fn new(format: SignedCompressionType) -> Bc5Encoder {
+        Bc5Encoder { format }
+    }
+
+    /// The compression type of the surface. Typeless surfaces encode as unsigned.
+    pub fn format(&self) -> SignedCompressionType {
+        self.format
+    }
+}
+
+impl Encoder for Bc5Encoder {
+    fn encode_to(
+        &self,
+        input: EncoderInput<'_>,
+        surface: SurfaceDescriptor,
+        options: EncoderOptions,
+        buffer: &mut Vec<u8>,
+    ) -> EncoderResult<()> {
+        let signed = self.format == SignedCompressionType::SignedNormalized;
+        encode_block_surface(input, surface, BC5_LAYOUT, buffer, |texels, block| {
+            for (channel, block) in block.chunks_exact_mut(8).enumerate() {
+                let mut values = [0.0; 16];
+                for (value, texel) in values.iter_mut().zip(texels) {
+                    *value = texel[channel];
+                }
+                block.copy_from_slice(&encode_channel_block(&values, signed, options.quality));
+            }
+        })
+    }
+}
+
+/// Computes the Z component of a unit normal from its normalized X and Y channels.
+pub(crate) fn reconstruct_z(r: f32, g: f32, signed: bool) -> f32 {
+    if signed {
+        (1.0 - r * r - g * g).max(0.0).sqrt()
+    } else {
+        let (x, y) = (r * 2.0 - 1.0, g * 2.0 - 1.0);
+        (1.0 - x * x - y * y).max(0.0).sqrt() * 0.5 + 0.5
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An unsigned block with solid 1.0 red and green at index 2 of the 6-value palette (0.2).
+    const BLOCK: [u8; 16] = [
+        0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // red: index 0 everywhere
+        0x00, 0xFF, 0x92, 0x24, 0x49, 0x92, 0x24, 0x49, // green: index 2 everywhere
+    ];
+
+    #[test]
+    fn decodes_unsigned_channels() {
+        let texels = Bc5Decoder::new(SignedCompressionType::UnsignedNormalized)
+            .decode(&BLOCK, SurfaceDescriptor::new(4, 4))
+            .unwrap();
+        assert_eq!(texels.len(), 16 * 2);
+        assert!(texels.chunks_exact(2).all(|texel| texel == [255, 51]));
+    }
+
+    #[test]
+    fn decodes_signed_channels() {
+        let mut block = [0; 16];
+        block[..2].copy_from_slice(&[0x7F, 0x81]);
+        block[8..10].copy_from_slice(&[0x81, 0x7F]);
+        let texels = Bc5Decoder::new(SignedCompressionType::SignedNormalized)
+            .decode(&block, SurfaceDescriptor::new(4, 4))
+            .unwrap();
+        assert!(texels.chunks_exact(2).all(|texel| texel == [0x7F, 0x81]));
+
+        // The same bytes read as signed: red is 6-value between -1 and 0, green 8-value
+        // between 0 and -1
+        let texels = Bc5Decoder::new(SignedCompressionType::SignedNormalized)
+            .channel_type(ChannelType::Float32)
+            .decode(&BLOCK, SurfaceDescriptor::new(4, 4))
+            .unwrap();
+        let red = f32::from_le_bytes([texels[0], texels[1], texels[2], texels[3]]);
+        let green = f32::from_le_bytes([texels[4], texels[5], texels[6], texels[7]]);
+        assert_eq!(red, -1.0 / 127.0);
+        assert!((green + 1.0 / 7.0 / 127.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reconstructs_z() {
+        assert_eq!(reconstruct_z(0.0, 0.0, true), 1.0);
+        assert!((reconstruct_z(0.6, 0.0, true) - 0.8).abs() < 1e-6);
+        assert_eq!(reconstruct_z(0.5, 0.5, false), 1.0);
+        assert!((reconstruct_z(0.5, 0.8, false) - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reconstruct_z_clamps_outside_unit_circle() {
+        // x² + y² > 1 would take the root of a negative number
+        assert_eq!(reconstruct_z(1.0, 1.0, true), 0.0);
+        assert_eq!(reconstruct_z(-1.0, 0.5, true), 0.0);
+        assert_eq!(reconstruct_z(1.0, 0.0, false), 0.5);
+        assert_eq!(reconstruct_z(0.0, 1.0, false), 0.5);
+
+        // Unsigned BLOCK has x = 1 and y = -0.6
+        let texels = Bc5Decoder::new(SignedCompressionType::UnsignedNormalized)
+            .channel_type(ChannelType::Float32)
+            .reconstruct_z(true)
+            .decode(&BLOCK, SurfaceDescriptor::new(4, 4))
+            .unwrap();
+        assert_eq!(texels.len(), 16 * 12);
+        for texel in texels.chunks_exact(12) {
+            let blue = f32::from_le_bytes([texel[8], texel[9], texel[10], texel[11]]);
+            assert_eq!(blue, 0.5);
+        }
+    }
+
+    #[test]
+    fn round_trips_encoded_channels() {
+        // A 6x5 surface with independent red and green gradients
+        let (width, height) = (6, 5);
+        let pixel = |i: usize| {
+            let (x, y) = ((i % width) as f32, (i / width) as f32);
+            [x / 5.0, 1.0 - y / 4.0]
+        };
+        let surface = SurfaceDescriptor::new(width, height);
+
+        for format in [
+            SignedCompressionType::UnsignedNormalized,
+            SignedCompressionType::SignedNormalized,
+        ] {
+            let signed = format == SignedCompressionType::SignedNormalized;
+            let to_range = |v: f32| if signed { v * 2.0 - 1.0 } else { v };
+            let values: Vec<f32> = (0..width * height)
+                .flat_map(|i| pixel(i).map(to_range))
+                .collect();
+            let encoded = Bc5Encoder::new(format)
+                .encode(
+                    EncoderInput::Rg32F(&values),
+                    surface,
+                    EncoderOptions::default(),
+                )
+                .unwrap();
+            assert_eq!(encoded.len(), 2 * 2 * 16);
+
+            let decoded = Bc5Decoder::new(format)
+                .channel_type(ChannelType::Float32)
+                .decode(&encoded, surface)
+                .unwrap();
+            for (texel, expected) in decoded.chunks_exact(8).zip(values.chunks_exact(2)) {
+                for c in 0..2 {
+                    let bytes = &texel[c * 4..c * 4 + 4];
+                    let value = f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    // Blocks span at most 0.6, or 1.2 when signed, so values are within half
+                    // of a 7th of that
+                    let tolerance = if signed { 0.6 / 7.0 } else { 0.3 / 7.0 };
+                    assert!(
+                        (value - expected[c]).abs() <= tolerance,
+                        "{:?}: {} instead of {}",
+                        format,
+                        value,
+                        expected[c]
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1094 @@
+use crate::{
+    bptc::{is_anchor, subset, weights, BitReader},
+    decode_block_at, decode_block_surface,
+    float::f16_to_f32,
+    BlockLayout, Blocks, ChannelType, DecodedBlock, Decoder, DecoderResult, SurfaceDescriptor,
+    TexelFormat,
+};
+use ddsfmt::BC6HCompressionType;
+
+pub(crate) const BC6H_LAYOUT: BlockLayout = BlockLayout::new(4, 4, 16);
+
+/// Decoder for BC6H surfaces, producing RGB texels.
+///
+/// Texels are written as [`ChannelType::Float16`] by default. Normalized channel types clamp the
+/// HDR values to the normalized range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc6hDecoder {
+    format: BC6HCompressionType,
+    channel_type: ChannelType,
+}
+
+impl Bc6hDecoder {
+    pub fn new(format: BC6HCompressionType) -> Bc6hDecoder {
+        Bc6hDecoder {
+            format,
+            channel_type: ChannelType::Float16,
+        }
+    }
+
+    /// Sets the storage of each decoded channel.
+    pub fn channel_type(mut self, channel_type: ChannelType) -> Bc6hDecoder {
+        self.channel_type = channel_type;
+        self
+    }
+
+    /// The compression type of the surface. Typeless surfaces decode as unsigned.
+    pub fn format(&self) -> BC6HCompressionType {
+        self.format
+    }
+
+    fn texel_format(&self) -> TexelFormat {
+        let signed = self.format == BC6HCompressionType::SignedFloat16;
+        TexelFormat::new(3, self.channel_type, signed)
+    }
+
+    fn decode_block(&self, block: &[u8], texels: &mut [u8]) {
+        let format = self.texel_format();
+        let size = self.channel_type.size();
+        let values = decode_bc6h_block(block, format.signed);
+        for (value, texel) in values.iter().zip(texels.chunks_exact_mut(format.size())) {
+            for (channel, out) in value.iter().zip(texel.chunks_exact_mut(size)) {
+                self.channel_type
+                    .write(f16_to_f32(*channel), format.signed, out);
+            }
+        }
+    }
+}
+
+impl Decoder for Bc6hDecoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(
+            bytes,
+            surface,
+            BC6H_LAYOUT,
+            self.texel_format().size(),
+            buffer,
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            BC6H_LAYOUT,
+            self.texel_format(),
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, BC6H_LAYOUT)
+    }
+}
+
+/// A run of endpoint bits in a mode's bit layout: `(field, first bit, bit count)`.
+///
+/// Fields are numbered `endpoint * 3 + channel`, where endpoints 0 and 1 belong to the first
+/// region and endpoints 2 and 3 to the second.
+type BitRun = (u8, u8, u8);
+
+const RW: u8 = 0;
+const GW: u8 = 1;
+const BW: u8 = 2;
+const RX: u8 = 3;
+const GX: u8 = 4;
+const BX: u8 = 5;
+const RY: u8 = 6;
+const GY: u8 = 7;
+const BY: u8 = 8;
+const RZ: u8 = 9;
+const GZ: u8 = 10;
+const BZ: u8 = 11;
+
+struct Mode {
+    /// Number of regions, each with its own pair of endpoints.
+    regions: usize,
+    /// Whether endpoints other than the first are stored as deltas from the first.
+    transformed: bool,
+    /// Precision of the endpoints in bits.
+    endpoint_bits: u32,
+    /// Precision of each channel of the stored deltas or secondary endpoints.
+    delta_bits: [u32; 3],
+    /// Bit layout of the endpoints, following the mode bits.
+    layout: &'static [BitRun],
+}
+
+#[rustfmt::skip]
+const MODES: [Mode; 14] = [
+    Mode {
+        regions: 2, transformed: true, endpoint_bits: 10, delta_bits: [5, 5, 5],
+        layout: &[
+            (GY, 4, 1), (BY, 4, 1), (BZ, 4, 1), (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5),
+            (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1),
+            (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        regions: 2, transformed: true, endpoint_bits: 7, delta_bits: [6, 6, 6],
+        layout: &[
+            (GY, 5, 1), (GZ, 4, 2), (RW, 0, 7), (BZ, 0, 2), (BY, 4, 1), (GW, 0, 7), (BY, 5, 1),
+            (BZ, 2, 1), (GY, 4, 1), (BW, 0, 7), (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 6),
+            (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6),
+        ],
+    },
+    Mode {
+        regions: 2, transformed: true, endpoint_bits: 11, delta_bits: [5, 4, 4],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 5), (RW, 10, 1), (GY, 0, 4),
+            (GX, 0, 4), (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1),
+            (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        regions: 2, transformed: true, endpoint_bits: 11, delta_bits: [4, 5, 4],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (GZ, 4, 1),
+            (GY, 0, 4), (GX, 0, 5), (GW, 10, 1), (GZ, 0, 4), (BX, 0, 4), (BW, 10, 1), (BZ, 1, 1),
+            (BY, 0, 4), (RY, 0, 4), (BZ, 0, 1), (BZ, 2, 1), (RZ, 0, 4), (GY, 4, 1), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        regions: 2, transformed: true, endpoint_bits: 11, delta_bits: [4, 4, 5],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 10, 1), (BY, 4, 1),
+            (GY, 0, 4), (GX, 0, 4), (GW, 10, 1), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BW, 10, 1),
+            (BY, 0, 4), (RY, 0, 4), (BZ, 1, 2), (RZ, 0, 4), (BZ, 4, 1), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        regions: 2, transformed: true, endpoint_bits: 9, delta_bits: [5, 5, 5],
+        layout: &[
+            (RW, 0, 9), (BY, 4, 1), (GW, 0, 9), (GY, 4, 1), (BW, 0, 9), (BZ, 4, 1), (RX, 0, 5),
+            (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5), (BZ, 1, 1),
+            (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        regions: 2, transformed: true, endpoint_bits: 8, delta_bits: [6, 5, 5],
+        layout: &[
+            (RW, 0, 8), (GZ, 4, 1), (BY, 4, 1), (GW, 0, 8), (BZ, 2, 1), (GY, 4, 1), (BW, 0, 8),
+            (BZ, 3, 2), (RX, 0, 6), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1), (GZ, 0, 4), (BX, 0, 5),
+            (BZ, 1, 1), (BY, 0, 4), (RY, 0, 6), (RZ, 0, 6),
+        ],
+    },
+    Mode {
+        regions: 2, transformed: true, endpoint_bits: 8, delta_bits: [5, 6, 5],
+        layout: &[
+            (RW, 0, 8), (BZ, 0, 1), (BY, 4, 1), (GW, 0, 8), (GY, 5, 1), (GY, 4, 1), (BW, 0, 8),
+            (GZ, 5, 1), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4),
+            (BX, 0, 5), (BZ, 1, 1), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        regions: 2, transformed: true, endpoint_bits: 8, delta_bits: [5, 5, 6],
+        layout: &[
+            (RW, 0, 8), (BZ, 1, 1), (BY, 4, 1), (GW, 0, 8), (BY, 5, 1), (GY, 4, 1), (BW, 0, 8),
+            (BZ, 5, 1), (BZ, 4, 1), (RX, 0, 5), (GZ, 4, 1), (GY, 0, 4), (GX, 0, 5), (BZ, 0, 1),
+            (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 5), (BZ, 2, 1), (RZ, 0, 5), (BZ, 3, 1),
+        ],
+    },
+    Mode {
+        regions: 2, transformed: false, endpoint_bits: 6, delta_bits: [6, 6, 6],
+        layout: &[
+            (RW, 0, 6), (GZ, 4, 1), (BZ, 0, 2), (BY, 4, 1), (GW, 0, 6), (GY, 5, 1), (BY, 5, 1),
+            (BZ, 2, 1), (GY, 4, 1), (BW, 0, 6), (GZ, 5, 1), (BZ, 3, 1), (BZ, 5, 1), (BZ, 4, 1),
+            (RX, 0, 6), (GY, 0, 4), (GX, 0, 6), (GZ, 0, 4), (BX, 0, 6), (BY, 0, 4), (RY, 0, 6),
+            (RZ, 0, 6),
+        ],
+    },
+    Mode {
+        regions: 1, transformed: false, endpoint_bits: 10, delta_bits: [10, 10, 10],
+        layout: &[(RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 10), (GX, 0, 10), (BX, 0, 10)],
+    },
+    Mode {
+        regions: 1, transformed: true, endpoint_bits: 11, delta_bits: [9, 9, 9],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 9), (RW, 10, 1), (GX, 0, 9),
+            (GW, 10, 1), (BX, 0, 9), (BW, 10, 1),
+        ],
+    },
+    Mode {
+        regions: 1, transformed: true, endpoint_bits: 12, delta_bits: [8, 8, 8],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 8), (RW, 11, 1), (RW, 10, 1),
+            (GX, 0, 8), (GW, 11, 1), (GW, 10, 1), (BX, 0, 8), (BW, 11, 1), (BW, 10, 1),
+        ],
+    },
+    Mode {
+        regions: 1, transformed: true, endpoint_bits: 16, delta_bits: [4, 4, 4],
+        layout: &[
+            (RW, 0, 10), (GW, 0, 10), (BW, 0, 10), (RX, 0, 4), (RW, 15, 1), (RW, 14, 1),
+            (RW, 13, 1), (RW, 12, 1), (RW, 11, 1), (RW, 10, 1), (GX, 0, 4), (GW, 15, 1),
+            (GW, 14, 1), (GW, 13, 1), (GW, 12, 1), (GW, 11, 1), (GW, 10, 1), (BX, 0, 4),
+            (BW, 15, 1), (BW, 14, 1), (BW, 13, 1), (BW, 12, 1), (BW, 11, 1), (BW, 10, 1),
+        ],
+    },
+];
+
+/// Reads the mode bits of a block, returning `None` for the reserved modes.
+fn read_mode(reader: &mut BitReader) -> Option<&'static Mode> {
+    let low = reader.read(2);
+    if low < 2 {
+        return Some(&MODES[low as usize]);
+    }
+    match (reader.read(3) << 2) | low {
+        0b00010 => Some(&MODES[2]),
+        0b00110 => Some(&MODES[3]),
+        0b01010 => Some(&MODES[4]),
+        0b01110 => Some(&MODES[5]),
+        0b10010 => Some(&MODES[6]),
+        0b10110 => Some(&MODES[7]),
+        0b11010 => Some(&MODES[8]),
+        0b11110 => Some(&MODES[9]),
+        0b00011 => Some(&MODES[10]),
+        0b00111 => Some(&MODES[11]),
+        0b01011 => Some(&MODES[12]),
+        0b01111 => Some(&MODES[13]),
+        _ => None,
+    }
+}
+
+fn sign_extend(value: i32, bits: u32) -> i32 {
+    let shift = 32 - bits;
+    (value << shift) >> shift
+}
+
+fn unquantize(value: i32, bits: u32, signed: bool) -> i32 {
+    if signed {
+        if bits >= 16 {
+            return value;
+        }
+        let magnitude = value.abs();
+        let unquantized = if magnitude == 0 {
+            0
+        } else if magnitude >= (1 << (bits - 1)) - 1 {
+            0x7FFF
+        } else {
+            ((magnitude << 15) + 0x4000) >> (bits - 1)
+        };
+        if value < 0 {
+            -unquantized
+        } else {
+            unquantized
+        }
+    } else if bits >= 15 {
+        value
+    } else if value == 0 {
+        0
+    } else if value == (1 << bits) - 1 {
+        0xFFFF
+    } else {
+        ((value << 16) + 0x8000) >> bits
+    }
+}
+
+/// Scales an interpolated value to the bits of a half float.
+fn finish_unquantize(value: i32, signed: bool) -> u16 {
+    if signed {
+        let magnitude = ((value.abs() * 31) >> 5) as u16;
+        if value < 0 && magnitude != 0 {
+            0x8000 | magnitude
+        } else {
+            magnitude
+        }
+    } else {
+        ((value * 31) >> 6) as u16
+    }
+}
+
+/// Decodes a 16-byte BC6H block into 16 row-major RGB texels, as half float bits.
+///
+/// Blocks using one of the reserved modes decode to zero.
+pub(crate) fn decode_bc6h_block(block: &[u8], signed: bool) -> [[u16; 3]; 16] {
+    let mut texels = [[0; 3]; 16];
+    let mut reader = BitReader::new(block);
+    let mode = match read_mode(&mut reader) {
+        Some(mode) => mode,
+        None => return texels,
+    };
+
+    let mut endpoints = [[0i32; 3]; 4];
+    for &(field, first, count) in mode.layout {
+        let value = reader.read(count as u32) as i32;
+        endpoints[field as usize / 3][field as usize % 3] |= value << first;
+    }
+
+    let regions = mode.regions;
+    let (partition, index_bits) = if regions == 2 {
+        (reader.read(5) as usize, 3)
+    } else {
+        (0, 4)
+    };
+    let mut indices = [0; 16];
+    for (i, index) in indices.iter_mut().enumerate() {
+        let anchor = is_anchor(regions, partition, i);
+        *index = reader.read(index_bits - anchor as u32) as usize;
+    }
+
+    let bits = mode.endpoint_bits;
+    let mask = (1 << bits) - 1;
+    if signed {
+        for channel in endpoints[0].iter_mut() {
+            *channel = sign_extend(*channel, bits);
+        }
+    }
+    let (base, others) = endpoints.split_at_mut(1);
+    for endpoint in others.iter_mut().take(regions * 2 - 1) {
+        for (c, channel) in endpoint.iter_mut().enumerate() {
+            if mode.transformed {
+                let delta = sign_extend(*channel, mode.delta_bits[c]);
+                let value = (base[0][c] + delta) & mask;
+                *channel = if signed {
+                    sign_extend(value, bits)
+                } else {
+                    value
+                };
+            } else if signed {
+                *channel = sign_extend(*channel, bits);
+            }
+        }
+    }
+    for endpoint in endpoints.iter_mut() {
+        for channel in endpoint.iter_mut() {
+            *channel = unquantize(*channel, bits, signed);
+        }
+    }
+
+    let weights = weights(index_bits);
+    for (i, texel) in texels.iter_mut().enumerate() {
+        let region = subset(regions, partition, i);
+        let (e0, e1) = (endpoints[region * 2], endpoints[region * 2 + 1]);
+        let weight = weights[indices[i]] as i32;
+        for c in 0..3 {
+            let value = (e0[c] * (64 - weight) + e1[c] * weight + 32) >> 6;
+            texel[c] = finish_unquantize(value, signed);
+        }
+    }
+    texels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One block per mode, in mode table order, checked against an independent decoder. Every mode
+    // except the two untransformed ones stores its second and later endpoints as deltas.
+    const UNSIGNED_BLOCKS: [([u8; 16], [[u16; 3]; 16]); 14] = [
+        (
+            [
+                0x88, 0x70, 0x39, 0x11, 0xe4, 0x06, 0xb1, 0x2e, 0xd8, 0xfa, 0x73, 0xeb, 0x0c, 0x8e,
+                0xf9, 0x13,
+            ],
+            [
+                [0x6d0b, 0x4bdd, 0x3f07],
+                [0x6c8f, 0x4cd5, 0x3eaa],
+                [0x6db6, 0x4c54, 0x3e21],
+                [0x6c7f, 0x4c7d, 0x3f90],
+                [0x6c1a, 0x4c8a, 0x4005],
+                [0x6cfa, 0x4c00, 0x3efa],
+                [0x6cd7, 0x4c46, 0x3ee0],
+                [0x6e7f, 0x4c3a, 0x3d36],
+                [0x6c1a, 0x4c8a, 0x4005],
+                [0x6cfa, 0x4c00, 0x3efa],
+                [0x6ca0, 0x4cb2, 0x3eb7],
+                [0x6ce3, 0x4c70, 0x3f1a],
+                [0x6bb6, 0x4c97, 0x407b],
+                [0x6bb6, 0x4c97, 0x407b],
+                [0x6cc3, 0x4c6c, 0x3ed1],
+                [0x6d0b, 0x4bdd, 0x3f07],
+            ],
+        ),
+        (
+            [
+                0xb5, 0xa7, 0xcc, 0xe9, 0x3d, 0x32, 0xc7, 0xe7, 0x5c, 0x60, 0xf4, 0x05, 0x6a, 0x02,
+                0x07, 0x72,
+            ],
+            [
+                [0x3c88, 0x17bf, 0x6177],
+                [0x425c, 0x11ec, 0x0364],
+                [0x3e70, 0x15d7, 0x42ad],
+                [0x2c97, 0x0f9b, 0x4eb2],
+                [0x3b94, 0x18b4, 0x70dc],
+                [0x3d7c, 0x16cb, 0x5212],
+                [0x36ac, 0x0615, 0x25cf],
+                [0x2c97, 0x0f9b, 0x4eb2],
+                [0x3c88, 0x17bf, 0x6177],
+                [0x3b94, 0x18b4, 0x70dc],
+                [0x3920, 0x03c4, 0x1bdd],
+                [0x2c97, 0x0f9b, 0x4eb2],
+                [0x3b94, 0x18b4, 0x70dc],
+                [0x2f0b, 0x0d4a, 0x44c0],
+                [0x3920, 0x03c4, 0x1bdd],
+                [0x2c97, 0x0f9b, 0x4eb2],
+            ],
+        ),
+        (
+            [
+                0x22, 0xab, 0x18, 0x77, 0x4c, 0x75, 0xfd, 0x74, 0x52, 0xc2, 0xe9, 0x31, 0xdf, 0x52,
+                0x63, 0x40,
+            ],
+            [
+                [0x5312, 0x21e9, 0x227b],
+                [0x5363, 0x21bc, 0x223d],
+                [0x5326, 0x21de, 0x226c],
+                [0x533c, 0x21d2, 0x225b],
+                [0x536c, 0x21ad, 0x22d1],
+                [0x5329, 0x21ef, 0x2306],
+                [0x5356, 0x21c3, 0x22e2],
+                [0x5356, 0x21c3, 0x22e2],
+                [0x536c, 0x21ad, 0x22d1],
+                [0x533f, 0x21d9, 0x22f5],
+                [0x5334, 0x21e5, 0x22fe],
+                [0x5376, 0x21a2, 0x22c8],
+                [0x5356, 0x21c3, 0x22e2],
+                [0x5376, 0x21a2, 0x22c8],
+                [0x5376, 0x21a2, 0x22c8],
+                [0x536c, 0x21ad, 0x22d1],
+            ],
+        ),
+        (
+            [
+                0xa6, 0x0d, 0x20, 0xd7, 0x62, 0xc9, 0x52, 0x45, 0xfb, 0xb2, 0x5e, 0x26, 0xc2, 0xbc,
+                0xd6, 0x3d,
+            ],
+            [
+                [0x0687, 0x22a6, 0x15db],
+                [0x0674, 0x2278, 0x15bf],
+                [0x067d, 0x228e, 0x15cc],
+                [0x0698, 0x22d1, 0x15f5],
+                [0x0684, 0x230f, 0x15ac],
+                [0x068f, 0x22bc, 0x15e8],
+                [0x06a1, 0x22e7, 0x1602],
+                [0x0687, 0x22a6, 0x15db],
+                [0x0695, 0x22fa, 0x15b2],
+                [0x06ee, 0x228a, 0x15d3],
+                [0x068f, 0x22bc, 0x15e8],
+                [0x0687, 0x22a6, 0x15db],
+                [0x06cb, 0x22b6, 0x15c6],
+                [0x06a7, 0x22e4, 0x15b9],
+                [0x06ee, 0x228a, 0x15d3],
+                [0x0698, 0x22d1, 0x15f5],
+            ],
+        ),
+        (
+            [
+                0x4a, 0xc9, 0x3b, 0x43, 0x0e, 0xe1, 0xc2, 0x71, 0xc8, 0xa9, 0x86, 0xe3, 0x56, 0x14,
+                0x60, 0xd7,
+            ],
+            [
+                [0x2385, 0x644b, 0x6e8e],
+                [0x2382, 0x643c, 0x6e87],
+                [0x2392, 0x64a8, 0x6eb5],
+                [0x2382, 0x643c, 0x6e87],
+                [0x23b1, 0x63c0, 0x6dcd],
+                [0x2390, 0x6499, 0x6eaf],
+                [0x2387, 0x645a, 0x6e94],
+                [0x2385, 0x644b, 0x6e8e],
+                [0x23bc, 0x6419, 0x6dc2],
+                [0x23bc, 0x6419, 0x6dc2],
+                [0x2382, 0x643c, 0x6e87],
+                [0x2382, 0x643c, 0x6e87],
+                [0x23b3, 0x63d1, 0x6dcb],
+                [0x23b3, 0x63d1, 0x6dcb],
+                [0x23b5, 0x63e3, 0x6dc8],
+                [0x2390, 0x6499, 0x6eaf],
+            ],
+        ),
+        (
+            [
+                0x6e, 0xca, 0x36, 0xa3, 0x72, 0xdb, 0x27, 0x76, 0x96, 0x44, 0x5c, 0xa8, 0x8f, 0xeb,
+                0xc8, 0x75,
+            ],
+            [
+                [0x15a7, 0x1a50, 0x52f6],
+                [0x1689, 0x1839, 0x5160],
+                [0x16e3, 0x19cb, 0x4e97],
+                [0x16c0, 0x192e, 0x4fae],
+                [0x16a8, 0x1a2b, 0x53d3],
+                [0x1667, 0x179d, 0x5277],
+                [0x16d1, 0x197c, 0x4f22],
+                [0x1678, 0x17eb, 0x51eb],
+                [0x16a8, 0x1a2b, 0x53d3],
+                [0x1678, 0x17eb, 0x51eb],
+                [0x16d1, 0x197c, 0x4f22],
+                [0x16c0, 0x192e, 0x4fae],
+                [0x1722, 0x1a1a, 0x543c],
+                [0x1689, 0x1839, 0x5160],
+                [0x1678, 0x17eb, 0x51eb],
+                [0x16d1, 0x197c, 0x4f22],
+            ],
+        ),
+        (
+            [
+                0x72, 0x3d, 0x57, 0x79, 0xdc, 0xb6, 0x54, 0x3b, 0x0b, 0x55, 0x81, 0x78, 0xd4, 0xe7,
+                0x9e, 0x80,
+            ],
+            [
+                [0x7212, 0x5486, 0x1d4e],
+                [0x7212, 0x5486, 0x1d4e],
+                [0x6278, 0x54dd, 0x1c9f],
+                [0x12bf, 0x569a, 0x1924],
+                [0x4346, 0x558b, 0x1b42],
+                [0x31f1, 0x55ec, 0x1a81],
+                [0x52df, 0x5534, 0x1bf1],
+                [0x676a, 0x519e, 0x1702],
+                [0x4346, 0x558b, 0x1b42],
+                [0x6940, 0x51af, 0x1881],
+                [0x6b17, 0x51c0, 0x1a01],
+                [0x676a, 0x519e, 0x1702],
+                [0x6cee, 0x51d2, 0x1b80],
+                [0x747e, 0x521a, 0x21aa],
+                [0x747e, 0x521a, 0x21aa],
+                [0x70d0, 0x51f7, 0x1eaa],
+            ],
+        ),
+        (
+            [
+                0x56, 0x78, 0x2d, 0x6b, 0x12, 0x4d, 0x69, 0x60, 0xe7, 0x87, 0x49, 0x6d, 0xeb, 0x8d,
+                0xbd, 0x28,
+            ],
+            [
+                [0x5e7b, 0x2d32, 0x19ea],
+                [0x5ec5, 0x2ea2, 0x19ea],
+                [0x5e7b, 0x2d32, 0x19ea],
+                [0x5da2, 0x3162, 0x1989],
+                [0x5e9e, 0x2de1, 0x19ea],
+                [0x5da2, 0x3162, 0x1989],
+                [0x61a9, 0x2dc9, 0x1af9],
+                [0x657a, 0x2a62, 0x1c56],
+                [0x6391, 0x2c15, 0x1ba7],
+                [0x57ea, 0x367e, 0x177e],
+                [0x5da2, 0x3162, 0x1989],
+                [0x657a, 0x2a62, 0x1c56],
+                [0x61a9, 0x2dc9, 0x1af9],
+                [0x57ea, 0x367e, 0x177e],
+                [0x61a9, 0x2dc9, 0x1af9],
+                [0x57ea, 0x367e, 0x177e],
+            ],
+        ),
+        (
+            [
+                0x9a, 0xf4, 0xe0, 0x4d, 0xba, 0x81, 0x41, 0x32, 0x58, 0x30, 0x13, 0x80, 0x0a, 0x7a,
+                0x13, 0x5c,
+            ],
+            [
+                [0x4fae, 0x5dba, 0x12a6],
+                [0x4f11, 0x5e8b, 0x10bd],
+                [0x4fae, 0x5dba, 0x12a6],
+                [0x4fae, 0x5dba, 0x12a6],
+                [0x5221, 0x5837, 0x0a8a],
+                [0x4e74, 0x5f5c, 0x0ed5],
+                [0x4f11, 0x5e8b, 0x10bd],
+                [0x4fae, 0x5dba, 0x12a6],
+                [0x54ac, 0x5685, 0x0b2d],
+                [0x4b52, 0x638a, 0x0516],
+                [0x4c8b, 0x61e7, 0x08e6],
+                [0x4f11, 0x5e8b, 0x10bd],
+                [0x54ac, 0x5685, 0x0b2d],
+                [0x557e, 0x55fa, 0x0b62],
+                [0x4b52, 0x638a, 0x0516],
+                [0x4e74, 0x5f5c, 0x0ed5],
+            ],
+        ),
+        (
+            [
+                0x1e, 0x84, 0xf7, 0x9b, 0xb0, 0xa2, 0x39, 0x17, 0x73, 0x3c, 0x9e, 0x32, 0xc5, 0x2b,
+                0xcd, 0x06,
+            ],
+            [
+                [0x36cb, 0x403d, 0x3521],
+                [0x6f22, 0x5d2e, 0x44a9],
+                [0x6f22, 0x5d2e, 0x44a9],
+                [0x6f22, 0x5d2e, 0x44a9],
+                [0x36cb, 0x403d, 0x3521],
+                [0x3985, 0x4981, 0x2c23],
+                [0x3c3e, 0x52c4, 0x2325],
+                [0x6dbd, 0x4f41, 0x1287],
+                [0x36cb, 0x403d, 0x3521],
+                [0x310b, 0x2caf, 0x481c],
+                [0x33c4, 0x35f2, 0x3f1e],
+                [0x2e51, 0x236b, 0x511a],
+                [0x33c4, 0x35f2, 0x3f1e],
+                [0x310b, 0x2caf, 0x481c],
+                [0x3c3e, 0x52c4, 0x2325],
+                [0x3ef8, 0x5c08, 0x1a28],
+            ],
+        ),
+        (
+            [
+                0xc3, 0xbc, 0xf1, 0xba, 0x04, 0x55, 0x3e, 0xa9, 0x88, 0x9c, 0x0a, 0xf4, 0xa4, 0x1c,
+                0x82, 0xc4,
+            ],
+            [
+                [0x40e5, 0x3b08, 0x40bb],
+                [0x46e0, 0x3b83, 0x3825],
+                [0x4cdc, 0x3bff, 0x2f8e],
+                [0x4849, 0x3ba0, 0x3620],
+                [0x4a0b, 0x3bc4, 0x3399],
+                [0x3ae9, 0x3a8c, 0x4952],
+                [0x40e5, 0x3b08, 0x40bb],
+                [0x516f, 0x3c5d, 0x28fd],
+                [0x40e5, 0x3b08, 0x40bb],
+                [0x4a0b, 0x3bc4, 0x3399],
+                [0x4cdc, 0x3bff, 0x2f8e],
+                [0x3c51, 0x3aa9, 0x474d],
+                [0x3e14, 0x3acd, 0x44c6],
+                [0x46e0, 0x3b83, 0x3825],
+                [0x40e5, 0x3b08, 0x40bb],
+                [0x4cdc, 0x3bff, 0x2f8e],
+            ],
+        ),
+        (
+            [
+                0x07, 0x67, 0xb7, 0x3b, 0xbe, 0x90, 0x81, 0x35, 0xe9, 0x23, 0x5f, 0xfb, 0xe2, 0x31,
+                0xa0, 0xbf,
+            ],
+            [
+                [0x704a, 0x3562, 0x7002],
+                [0x7139, 0x35df, 0x745c],
+                [0x7034, 0x3556, 0x6f9a],
+                [0x701e, 0x354a, 0x6f32],
+                [0x7150, 0x35ea, 0x74c3],
+                [0x7060, 0x356d, 0x7069],
+                [0x70f1, 0x35b9, 0x730b],
+                [0x7150, 0x35ea, 0x74c3],
+                [0x701e, 0x354a, 0x6f32],
+                [0x7139, 0x35df, 0x745c],
+                [0x7002, 0x353c, 0x6eb0],
+                [0x7034, 0x3556, 0x6f9a],
+                [0x6feb, 0x3530, 0x6e49],
+                [0x70db, 0x35ad, 0x72a3],
+                [0x7150, 0x35ea, 0x74c3],
+                [0x70f1, 0x35b9, 0x730b],
+            ],
+        ),
+        (
+            [
+                0x2b, 0xbb, 0x73, 0x7d, 0x97, 0x2c, 0xb7, 0x33, 0xb0, 0x9e, 0xb8, 0x37, 0xbf, 0x42,
+                0x5d, 0x0b,
+            ],
+            [
+                [0x4c55, 0x5482, 0x1d04],
+                [0x49e3, 0x52ee, 0x1f4e],
+                [0x4936, 0x527e, 0x1ff0],
+                [0x4a5b, 0x533b, 0x1ede],
+                [0x4a90, 0x535e, 0x1eac],
+                [0x49e3, 0x52ee, 0x1f4e],
+                [0x4ac6, 0x5380, 0x1e7a],
+                [0x4ba8, 0x5412, 0x1da6],
+                [0x4901, 0x525b, 0x2022],
+                [0x49e3, 0x52ee, 0x1f4e],
+                [0x4bdd, 0x5434, 0x1d74],
+                [0x4b73, 0x53ef, 0x1dd8],
+                [0x4979, 0x52a9, 0x1fb2],
+                [0x4b3e, 0x53cd, 0x1e0a],
+                [0x49e3, 0x52ee, 0x1f4e],
+                [0x4c55, 0x5482, 0x1d04],
+            ],
+        ),
+        (
+            [
+                0xaf, 0xbc, 0x4f, 0x30, 0x94, 0xc0, 0x6c, 0xc1, 0x28, 0x5e, 0x6f, 0xb1, 0xe4, 0xd8,
+                0xaf, 0xb0,
+            ],
+            [
+                [0x3eeb, 0x349d, 0x0ca4],
+                [0x3eea, 0x349d, 0x0ca3],
+                [0x3eeb, 0x349f, 0x0ca4],
+                [0x3eeb, 0x349d, 0x0ca4],
+                [0x3eeb, 0x349f, 0x0ca4],
+                [0x3eeb, 0x349d, 0x0ca4],
+                [0x3eea, 0x349d, 0x0ca3],
+                [0x3eeb, 0x349e, 0x0ca4],
+                [0x3eeb, 0x349d, 0x0ca4],
+                [0x3eeb, 0x349f, 0x0ca4],
+                [0x3eeb, 0x349e, 0x0ca4],
+                [0x3eeb, 0x349f, 0x0ca4],
+                [0x3eeb, 0x349f, 0x0ca4],
+                [0x3eeb, 0x349e, 0x0ca4],
+                [0x3eea, 0x349d, 0x0ca3],
+                [0x3eeb, 0x349e, 0x0ca4],
+            ],
+        ),
+    ];
+
+    const SIGNED_BLOCKS: [([u8; 16], [[u16; 3]; 16]); 14] = [
+        (
+            [
+                0x34, 0xd2, 0x6c, 0xd4, 0x5b, 0x4f, 0xc7, 0xe4, 0x59, 0x8d, 0x49, 0x24, 0xda, 0x75,
+                0xe4, 0xb6,
+            ],
+            [
+                [0xd841, 0x3444, 0x7767],
+                [0xd776, 0x33d5, 0x780d],
+                [0xd901, 0x34ad, 0x76cb],
+                [0xd6b5, 0x3287, 0x798a],
+                [0xd8a1, 0x3478, 0x7719],
+                [0xd752, 0x3290, 0x78a7],
+                [0xd7ef, 0x3299, 0x77c4],
+                [0xda75, 0x32bd, 0x7421],
+                [0xd752, 0x3290, 0x78a7],
+                [0xda75, 0x32bd, 0x7421],
+                [0xd619, 0x327f, 0x7a6d],
+                [0xd6b5, 0x3287, 0x798a],
+                [0xda75, 0x32bd, 0x7421],
+                [0xd9d8, 0x32b4, 0x7503],
+                [0xd9d8, 0x32b4, 0x7503],
+                [0xd752, 0x3290, 0x78a7],
+            ],
+        ),
+        (
+            [
+                0xad, 0x24, 0xee, 0x21, 0x82, 0x13, 0x4f, 0xba, 0x53, 0x66, 0x0f, 0x3e, 0x74, 0x89,
+                0x40, 0x5a,
+            ],
+            [
+                [0x3b94, 0xcd42, 0x1629],
+                [0x48a8, 0xc6b8, 0x1ff8],
+                [0x1c18, 0xd448, 0x86c8],
+                [0x5fe8, 0x9648, 0x9458],
+                [0x3b94, 0xcd42, 0x1629],
+                [0x1c18, 0xd448, 0x86c8],
+                [0x4cd5, 0xa7b8, 0x9087],
+                [0x3b94, 0xcd42, 0x1629],
+                [0x444c, 0xc8e6, 0x1cb3],
+                [0x25a1, 0xcb90, 0x88b0],
+                [0x2f2a, 0xc2d8, 0x8a98],
+                [0x48a8, 0xc6b8, 0x1ff8],
+                [0x434c, 0xb070, 0x8e9f],
+                [0x434c, 0xb070, 0x8e9f],
+                [0x2e04, 0xd40a, 0x0bfd],
+                [0x3ff0, 0xcb14, 0x196e],
+            ],
+        ),
+        (
+            [
+                0xe2, 0xb6, 0xdb, 0x37, 0xb9, 0xe2, 0x73, 0x7b, 0x4b, 0xff, 0xbd, 0x20, 0x00, 0x42,
+                0xb0, 0xf6,
+            ],
+            [
+                [0x34c3, 0x88f3, 0xe8fc],
+                [0x34c3, 0x88f3, 0xe8fc],
+                [0x3511, 0x88ea, 0xe930],
+                [0x3538, 0x88e6, 0xe94a],
+                [0x3511, 0x88ea, 0xe930],
+                [0x3538, 0x88e6, 0xe94a],
+                [0x3538, 0x88e6, 0xe94a],
+                [0x3538, 0x88e6, 0xe94a],
+                [0x3511, 0x88ea, 0xe930],
+                [0x3497, 0x88f7, 0xe8de],
+                [0x3538, 0x88e6, 0xe94a],
+                [0x3497, 0x88f7, 0xe8de],
+                [0x3537, 0x890a, 0xe9a2],
+                [0x3519, 0x8916, 0xe995],
+                [0x3519, 0x8916, 0xe995],
+                [0x3578, 0x88ee, 0xe9bd],
+            ],
+        ),
+        (
+            [
+                0x06, 0x32, 0x65, 0xc3, 0x47, 0x3d, 0x28, 0xe0, 0x8a, 0x65, 0x59, 0x69, 0x67, 0xa4,
+                0xf9, 0x24,
+            ],
+            [
+                [0x3039, 0x568e, 0x784e],
+                [0x2fcd, 0x569b, 0x784e],
+                [0x3039, 0x568e, 0x784e],
+                [0x3039, 0x568e, 0x784e],
+                [0x3016, 0x5693, 0x784e],
+                [0x2f87, 0x56a4, 0x784e],
+                [0x2ff0, 0x5697, 0x784e],
+                [0x305c, 0x568a, 0x784e],
+                [0x3039, 0x568e, 0x784e],
+                [0x3039, 0x568e, 0x784e],
+                [0x3016, 0x5693, 0x784e],
+                [0x3010, 0x559d, 0x786d],
+                [0x2f87, 0x56a4, 0x784e],
+                [0x3067, 0x5677, 0x78aa],
+                [0x3067, 0x5677, 0x78aa],
+                [0x311a, 0x5837, 0x7927],
+            ],
+        ),
+        (
+            [
+                0x6a, 0x69, 0x37, 0x89, 0xdc, 0x69, 0x2a, 0x28, 0x12, 0x47, 0x18, 0xd1, 0x66, 0x56,
+                0xbf, 0x4e,
+            ],
+            [
+                [0x9626, 0xb0a3, 0x45c0],
+                [0x96bd, 0xb03c, 0x44bc],
+                [0x96a7, 0xb038, 0x44fd],
+                [0x9679, 0xb02f, 0x4587],
+                [0x967f, 0xb06d, 0x44a1],
+                [0x964d, 0xb026, 0x460a],
+                [0x9679, 0xb02f, 0x4587],
+                [0x96bd, 0xb03c, 0x44bc],
+                [0x963b, 0xb095, 0x457a],
+                [0x9664, 0xb02b, 0x45c8],
+                [0x964d, 0xb026, 0x460a],
+                [0x9638, 0xb022, 0x464b],
+                [0x9669, 0xb07a, 0x44e7],
+                [0x964d, 0xb026, 0x460a],
+                [0x96bd, 0xb03c, 0x44bc],
+                [0x96bd, 0xb03c, 0x44bc],
+            ],
+        ),
+        (
+            [
+                0x6e, 0x5b, 0x25, 0xdc, 0xa8, 0x3a, 0x59, 0x43, 0x51, 0xff, 0x9e, 0x29, 0x16, 0x06,
+                0x60, 0xe7,
+            ],
+            [
+                [0x6812, 0x25ec, 0x36bf],
+                [0x6d83, 0x2a3f, 0x33d7],
+                [0x6c26, 0x29f9, 0x364b],
+                [0x6cd5, 0x2a1c, 0x3511],
+                [0x6992, 0x24b2, 0x35ee],
+                [0x65bd, 0x27d5, 0x3805],
+                [0x6cd5, 0x2a1c, 0x3511],
+                [0x6e32, 0x2a62, 0x329e],
+                [0x6812, 0x25ec, 0x36bf],
+                [0x6a52, 0x2416, 0x3586],
+                [0x6e32, 0x2a62, 0x329e],
+                [0x6e32, 0x2a62, 0x329e],
+                [0x6812, 0x25ec, 0x36bf],
+                [0x64fe, 0x2872, 0x386e],
+                [0x673d, 0x269b, 0x3734],
+                [0x6c26, 0x29f9, 0x364b],
+            ],
+        ),
+        (
+            [
+                0xf2, 0x4d, 0xf2, 0x54, 0x36, 0xa2, 0xb2, 0xa4, 0x0c, 0x69, 0x63, 0xdc, 0xc3, 0xc0,
+                0x0a, 0x35,
+            ],
+            [
+                [0x6c04, 0x9b9c, 0x292c],
+                [0x7102, 0xa4c4, 0x30aa],
+                [0x71d4, 0x9aa4, 0x1e84],
+                [0xda8d, 0x967a, 0x2457],
+                [0x7031, 0xa344, 0x2f70],
+                [0xfbff, 0x95cc, 0x254c],
+                [0x71d4, 0x9aa4, 0x1e84],
+                [0x7102, 0xa4c4, 0x30aa],
+                [0x6c04, 0x9b9c, 0x292c],
+                [0x71d4, 0x9aa4, 0x1e84],
+                [0x0d7e, 0x9898, 0x2160],
+                [0x7031, 0xa344, 0x2f70],
+                [0x71d4, 0x9aa4, 0x1e84],
+                [0x2ef0, 0x9947, 0x206c],
+                [0x7031, 0xa344, 0x2f70],
+                [0x6cd5, 0x9d1b, 0x2a65],
+            ],
+        ),
+        (
+            [
+                0x16, 0x12, 0xfa, 0x67, 0xb7, 0x0e, 0x27, 0x1b, 0x15, 0xe8, 0xa5, 0xcc, 0x97, 0xe8,
+                0x6e, 0xd6,
+            ],
+            [
+                [0xee58, 0x8d33, 0xcc70],
+                [0xefb5, 0x8e4a, 0xcdcd],
+                [0xee58, 0x8d33, 0xcc70],
+                [0xf112, 0x8f61, 0xcf2a],
+                [0xf54f, 0x92c5, 0xd367],
+                [0xf6ac, 0x93dc, 0xd4c4],
+                [0xefb5, 0x8e4a, 0xcdcd],
+                [0xefb5, 0x8e4a, 0xcdcd],
+                [0xf295, 0x9097, 0xd0ad],
+                [0xf54f, 0x92c5, 0xd367],
+                [0xf3f2, 0x91ae, 0xd20a],
+                [0xf112, 0x8f61, 0xcf2a],
+                [0xedb7, 0x9c97, 0xcc51],
+                [0xf885, 0xa4a5, 0xd5a4],
+                [0xea3e, 0x9a01, 0xc952],
+                [0xedb7, 0x9c97, 0xcc51],
+            ],
+        ),
+        (
+            [
+                0xda, 0x11, 0xf9, 0x91, 0xa2, 0x97, 0xa4, 0x23, 0xea, 0x01, 0x2f, 0x57, 0x67, 0xfa,
+                0x6d, 0xe8,
+            ],
+            [
+                [0xf3d3, 0x8c69, 0x4918],
+                [0xf231, 0x8cf5, 0x4824],
+                [0xf5c3, 0x94cc, 0x294b],
+                [0xf3db, 0x95c0, 0x29d6],
+                [0xf747, 0x8b43, 0x4b1b],
+                [0xf8e9, 0x8ab7, 0x4c0f],
+                [0xf08e, 0x8d80, 0x4730],
+                [0xf3db, 0x95c0, 0x29d6],
+                [0xf231, 0x8cf5, 0x4824],
+                [0xfa8c, 0x8a2c, 0x4d04],
+                [0xfa8c, 0x8a2c, 0x4d04],
+                [0xedec, 0x98b7, 0x2b88],
+                [0xf8e9, 0x8ab7, 0x4c0f],
+                [0xeeec, 0x8e0c, 0x463c],
+                [0xf231, 0x8cf5, 0x4824],
+                [0xfa8c, 0x8a2c, 0x4d04],
+            ],
+        ),
+        (
+            [
+                0x1e, 0x26, 0x82, 0xfe, 0xb9, 0x6f, 0x8f, 0xb9, 0x40, 0xae, 0xa0, 0x3c, 0xb3, 0xaf,
+                0x27, 0x15,
+            ],
+            [
+                [0xbff0, 0x1170, 0x85d0],
+                [0xb84f, 0x068a, 0x92e4],
+                [0xdb07, 0x0789, 0x1a37],
+                [0x6e70, 0xfbff, 0x3830],
+                [0xbc1f, 0x0bfd, 0x8c5a],
+                [0x9917, 0xa382, 0x2406],
+                [0x4d78, 0xe678, 0x3348],
+                [0x4d78, 0xe678, 0x3348],
+                [0xa4d0, 0x9550, 0xb450],
+                [0xba0f, 0x8dfc, 0x1f1f],
+                [0x6e70, 0xfbff, 0x3830],
+                [0xdb07, 0x0789, 0x1a37],
+                [0xdb07, 0x0789, 0x1a37],
+                [0x2c80, 0xd0f2, 0x2e61],
+                [0xba0f, 0x8dfc, 0x1f1f],
+                [0xfbff, 0x1d10, 0x1550],
+            ],
+        ),
+        (
+            [
+                0x23, 0x01, 0xaa, 0x7a, 0x92, 0xdd, 0xaf, 0x67, 0xa4, 0x69, 0x92, 0x94, 0x08, 0x53,
+                0xa5, 0xf2,
+            ],
+            [
+                [0x80b2, 0x53e5, 0x4925],
+                [0x8c04, 0x594c, 0x3afe],
+                [0x8a5a, 0x5881, 0x3d13],
+                [0x865b, 0x5698, 0x4212],
+                [0x80b2, 0x53e5, 0x4925],
+                [0x8a5a, 0x5881, 0x3d13],
+                [0x835c, 0x552a, 0x45d1],
+                [0x8a5a, 0x5881, 0x3d13],
+                [0x8905, 0x57de, 0x3ebd],
+                [0x024d, 0x5277, 0x4ce5],
+                [0x8207, 0x5487, 0x477b],
+                [0x84b1, 0x55cd, 0x4427],
+                [0x84b1, 0x55cd, 0x4427],
+                [0x8c04, 0x594c, 0x3afe],
+                [0x80b2, 0x53e5, 0x4925],
+                [0x9303, 0x5ca3, 0x3241],
+            ],
+        ),
+        (
+            [
+                0xa7, 0x40, 0x19, 0xc4, 0x03, 0xfa, 0xfb, 0x85, 0x20, 0xbb, 0xcf, 0xc0, 0xca, 0xf8,
+                0xd3, 0x6c,
+            ],
+            [
+                [0xbd74, 0xf601, 0x3a6d],
+                [0xc0b9, 0xf690, 0x3641],
+                [0xce87, 0xf8f0, 0x24a4],
+                [0xce87, 0xf8f0, 0x24a4],
+                [0xd4b4, 0xfa00, 0x1cc2],
+                [0xcffb, 0xf930, 0x22c9],
+                [0xbd74, 0xf601, 0x3a6d],
+                [0xcffb, 0xf930, 0x22c9],
+                [0xcd13, 0xf8b0, 0x267f],
+                [0xcffb, 0xf930, 0x22c9],
+                [0xc9ce, 0xf820, 0x2aaa],
+                [0xd4b4, 0xfa00, 0x1cc2],
+                [0xc22d, 0xf6d0, 0x3467],
+                [0xd16f, 0xf970, 0x20ef],
+                [0xcffb, 0xf930, 0x22c9],
+                [0xc6e6, 0xf7a1, 0x2e60],
+            ],
+        ),
+        (
+            [
+                0xeb, 0xeb, 0x1e, 0x97, 0x8a, 0x2d, 0x0a, 0x9b, 0xc4, 0x54, 0x2b, 0xf6, 0xff, 0x23,
+                0x54, 0x1b,
+            ],
+            [
+                [0xc873, 0x2369, 0xe787],
+                [0xcb96, 0x26a1, 0xe561],
+                [0xc90c, 0x2406, 0xe71e],
+                [0xc959, 0x2454, 0xe6ea],
+                [0xcb4a, 0x2653, 0xe596],
+                [0xc873, 0x2369, 0xe787],
+                [0xc9b8, 0x24b7, 0xe6a9],
+                [0xcc8f, 0x27a0, 0xe4b8],
+                [0xcc8f, 0x27a0, 0xe4b8],
+                [0xcc8f, 0x27a0, 0xe4b8],
+                [0xc8c0, 0x23b8, 0xe752],
+                [0xc873, 0x2369, 0xe787],
+                [0xc90c, 0x2406, 0xe71e],
+                [0xc959, 0x2454, 0xe6ea],
+                [0xcb4a, 0x2653, 0xe596],
+                [0xc813, 0x2307, 0xe7c8],
+            ],
+        ),
+        (
+            [
+                0xcf, 0xe2, 0xe4, 0x57, 0xbc, 0xe0, 0xb7, 0xde, 0xd8, 0x73, 0x58, 0x4d, 0x81, 0x38,
+                0x4e, 0x2d,
+            ],
+            [
+                [0xf900, 0xa315, 0xa4a7],
+                [0xf8fc, 0xa316, 0xa4a9],
+                [0xf901, 0xa315, 0xa4a7],
+                [0xf8ff, 0xa315, 0xa4a7],
+                [0xf8fe, 0xa316, 0xa4a8],
+                [0xf900, 0xa315, 0xa4a7],
+                [0xf8fc, 0xa316, 0xa4a9],
+                [0xf900, 0xa315, 0xa4a7],
+                [0xf902, 0xa315, 0xa4a6],
+                [0xf8fe, 0xa316, 0xa4a8],
+                [0xf8fe, 0xa316, 0xa4a8],
+                [0xf901, 0xa315, 0xa4a7],
+                [0xf8fb, 0xa316, 0xa4a9],
+                [0xf900, 0xa315, 0xa4a7],
+                [0xf8fc, 0xa316, 0xa4a9],
+                [0xf901, 0xa315, 0xa4a6],
+            ],
+        ),
+    ];
+
+    #[test]
+    fn decodes_unsigned_modes() {
+        for (mode, (block, expected)) in UNSIGNED_BLOCKS.iter().enumerate() {
+            assert_eq!(decode_bc6h_block(block, false), *expected, "mode {}", mode);
+        }
+    }
+
+    #[test]
+    fn decodes_signed_modes() {
+        for (mode, (block, expected)) in SIGNED_BLOCKS.iter().enumerate() {
+            assert_eq!(decode_bc6h_block(block, true), *expected, "mode {}", mode);
+        }
+    }
+
+    #[test]
+    fn reserved_modes_decode_to_zero() {
+        for &code in &[0x13, 0x17, 0x1b, 0x1f] {
+            let mut block = UNSIGNED_BLOCKS[0].0;
+            block[0] = (block[0] & !0x1f) | code;
+            assert_eq!(decode_bc6h_block(&block, false), [[0; 3]; 16]);
+            assert_eq!(decode_bc6h_block(&block, true), [[0; 3]; 16]);
+        }
+    }
+}
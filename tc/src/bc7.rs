@@ -0,0 +1,1354 @@
+use crate::{
+    bptc::{anchor, is_anchor, subset, weights, BitReader, BitWriter},
+    decode_block_at, decode_block_surface, encode_block_surface, BlockLayout, Blocks, DecodedBlock,
+    Decoder, DecoderResult, Encoder, EncoderInput, EncoderOptions, EncoderResult, Quality,
+    SurfaceDescriptor, TexelFormat,
+};
+use ddsfmt::BlockCompressionType;
+use std::ops::Range;
+
+pub(crate) const BC7_LAYOUT: BlockLayout = BlockLayout::new(4, 4, 16);
+
+/// Decoder for BC7 surfaces, producing RGBA8 texels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc7Decoder {
+    format: BlockCompressionType,
+}
+
+impl Bc7Decoder {
+    pub fn new(format: BlockCompressionType) -> Bc7Decoder {
+        Bc7Decoder { format }
+    }
+
+    /// The compression type of the surface. sRGB surfaces decode to sRGB-encoded texels.
+    pub fn format(&self) -> BlockCompressionType {
+        self.format
+    }
+
+    fn decode_block(&self, block: &[u8], texels: &mut [u8]) {
+        let values = decode_bc7_block(block);
+        for (value, texel) in values.iter().zip(texels.chunks_exact_mut(4)) {
+            texel.copy_from_slice(value);
+        }
+    }
+}
+
+impl Decoder for Bc7Decoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(bytes, surface, BC7_LAYOUT, 4, buffer, |block, texels| {
+            self.decode_block(block, texels)
+        })
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            BC7_LAYOUT,
+            TexelFormat::RGBA8,
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, BC7_LAYOUT)
+    }
+}
+
+/// Speed profile of a [`Bc7Encoder`], selecting the modes, partitions and rotations it searches.
+///
+/// Opaque blocks are searched with the modes leaving alpha implicit, 1, 3, 0 and 2, followed by
+/// mode 6, whose alpha endpoints and p-bits store an alpha of exactly 255 and which is the only
+/// mode fitting a single subset with 4-bit indices. [`Bc7Profile::Slow`] also tries modes 5 and 4,
+/// whose rotation gives one color channel indices of its own. Mode 7 is left out, as mode 3 stores
+/// any opaque mode 7 block exactly. Blocks with alpha are searched with the modes that store it.
+///
+/// Partitioned modes only encode the partitions with the lowest estimated error, and skip the rest
+/// once the estimate reaches the best error found. The estimate relies on a principal axis found
+/// by power iteration, so it is a heuristic rather than a strict bound, and a slower profile can
+/// occasionally encode a block with a higher error than a faster one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bc7Profile {
+    /// Mode 6 only, without refining its endpoints.
+    UltraFast,
+    /// Modes 1 and 6 for opaque blocks or 6 and 5 for blocks with alpha, with 2 partitions.
+    VeryFast,
+    /// Adds mode 3 for opaque blocks or mode 7 for blocks with alpha, with 8 partitions.
+    Fast,
+    /// Adds modes 0 and 2 for opaque blocks or mode 4 for blocks with alpha, with 16 partitions,
+    /// every rotation and index selection, and a search of the p-bits.
+    Basic,
+    /// Adds modes 5 and 4 for opaque blocks, with every partition, rotation, index selection and
+    /// p-bit combination.
+    Slow,
+}
+
+impl Bc7Profile {
+    fn settings(self) -> SearchSettings {
+        match self {
+            Bc7Profile::UltraFast => SearchSettings {
+                opaque_modes: &[6],
+                alpha_modes: &[6],
+                partitions: 1,
+                rotations: false,
+                pbit_search: false,
+                refinements: 0,
+            },
+            Bc7Profile::VeryFast => SearchSettings {
+                opaque_modes: &[1, 6],
+                alpha_modes: &[6, 5],
+                partitions: 2,
+                rotations: false,
+                pbit_search: false,
+                refinements: 1,
+            },
+            Bc7Profile::Fast => SearchSettings {
+                opaque_modes: &[1, 3, 6],
+                alpha_modes: &[6, 5, 7],
+                partitions: 8,
+                rotations: false,
+                pbit_search: false,
+                refinements: 1,
+            },
+            Bc7Profile::Basic => SearchSettings {
+                opaque_modes: &[1, 3, 0, 2, 6],
+                alpha_modes: &[6, 5, 4, 7],
+                partitions: 16,
+                rotations: true,
+                pbit_search: true,
+                refinements: 2,
+            },
+            Bc7Profile::Slow => SearchSettings {
+                opaque_modes: &[1, 3, 0, 2, 6, 5, 4],
+                alpha_modes: &[6, 5, 4, 7],
+                partitions: 64,
+                rotations: true,
+                pbit_search: true,
+                refinements: 4,
+            },
+        }
+    }
+}
+
+/// Search space of a [`Bc7Profile`].
+struct SearchSettings {
+    opaque_modes: &'static [usize],
+    alpha_modes: &'static [usize],
+    /// Number of partitions encoded by each partitioned mode.
+    partitions: usize,
+    /// Whether modes 4 and 5 try every rotation, and mode 4 both index selections.
+    rotations: bool,
+    /// Whether every p-bit combination is encoded, rather than only the one quantizing the
+    /// endpoints most closely.
+    pbit_search: bool,
+    /// Number of least squares refinements of the endpoints of each subset.
+    refinements: usize,
+}
+
+/// Encoder for BC7 surfaces.
+///
+/// The search of each block is set by a [`Bc7Profile`], which by default follows the [`Quality`]
+/// of each call: [`Bc7Profile::Fast`], [`Bc7Profile::Basic`] and [`Bc7Profile::Slow`] for
+/// [`Quality::Fast`], [`Quality::Normal`] and [`Quality::Slow`]. The endpoints of each subset are
+/// fitted to the principal axis of its texels, quantized with the best p-bits, then refined by
+/// least squares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bc7Encoder {
+    format: BlockCompressionType,
+    profile: Option<Bc7Profile>,
+}
+
+impl Bc7Encoder {
+    pub fn new(format: BlockCompressionType) -> Bc7Encoder {
+        Bc7Encoder {
+            format,
+            profile: None,
+        }
+    }
+
+    /// Sets the speed profile, overriding the quality of each call.
+    pub fn profile(mut self, profile: Bc7Profile) -> Bc7Encoder {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// The compression type of the surface. Texels are encoded as is for sRGB surfaces.
+    pub fn format(&self) -> BlockCompressionType {
+        self.format
+    }
+}
+
+impl Encoder for Bc7Encoder {
+    fn encode_to(
+        &self,
+        input: EncoderInput<'_>,
+        surface: SurfaceDescriptor,
+        options: EncoderOptions,
+        buffer: &mut Vec<u8>,
+    ) -> EncoderResult<()> {
+        let profile = self.profile.unwrap_or(match options.quality {
+            Quality::Fast => Bc7Profile::Fast,
+            Quality::Normal => Bc7Profile::Basic,
+            Quality::Slow => Bc7Profile::Slow,
+        });
+        let settings = profile.settings();
+        encode_block_surface(input, surface, BC7_LAYOUT, buffer, |texels, block| {
+            block.copy_from_slice(&encode_bc7_block(
+                texels,
+                &settings,
+                options.channel_weights,
+            ));
+        })
+    }
+}
+
+pub(crate) struct Mode {
+    pub subsets: usize,
+    pub partition_bits: u32,
+    pub rotation_bits: u32,
+    pub index_selection_bits: u32,
+    pub color_bits: u32,
+    pub alpha_bits: u32,
+    /// Whether each endpoint has its own p-bit.
+    pub endpoint_pbits: bool,
+    /// Whether both endpoints of a subset share a p-bit.
+    pub shared_pbits: bool,
+    pub index_bits: u32,
+    /// Bits of the secondary index set, or 0 if the mode has a single index set.
+    pub secondary_index_bits: u32,
+}
+
+#[rustfmt::skip]
+pub(crate) const MODES: [Mode; 8] = [
+    Mode {
+        subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4,
+        alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 3,
+        secondary_index_bits: 0,
+    },
+    Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6,
+        alpha_bits: 0, endpoint_pbits: false, shared_pbits: true, index_bits: 3,
+        secondary_index_bits: 0,
+    },
+    Mode {
+        subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5,
+        alpha_bits: 0, endpoint_pbits: false, shared_pbits: false, index_bits: 2,
+        secondary_index_bits: 0,
+    },
+    Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7,
+        alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 2,
+        secondary_index_bits: 0,
+    },
+    Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5,
+        alpha_bits: 6, endpoint_pbits: false, shared_pbits: false, index_bits: 2,
+        secondary_index_bits: 3,
+    },
+    Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7,
+        alpha_bits: 8, endpoint_pbits: false, shared_pbits: false, index_bits: 2,
+        secondary_index_bits: 2,
+    },
+    Mode {
+        subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7,
+        alpha_bits: 7, endpoint_pbits: true, shared_pbits: false, index_bits: 4,
+        secondary_index_bits: 0,
+    },
+    Mode {
+        subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5,
+        alpha_bits: 5, endpoint_pbits: true, shared_pbits: false, index_bits: 2,
+        secondary_index_bits: 0,
+    },
+];
+
+/// Expands a quantized channel of `bits` precision to 8 bits by bit replication.
+pub(crate) fn unquantize(value: u32, bits: u32) -> u8 {
+    let value = value << (8 - bits);
+    (value | (value >> bits)) as u8
+}
+
+/// Interpolates between two 8-bit endpoints with a BPTC weight.
+pub(crate) fn interpolate(e0: u8, e1: u8, weight: u32) -> u8 {
+    ((e0 as u32 * (64 - weight) + e1 as u32 * weight + 32) >> 6) as u8
+}
+
+/// Decodes a 16-byte BC7 block into 16 row-major RGBA8 texels.
+///
+/// Blocks with an invalid mode decode to transparent black.
+pub(crate) fn decode_bc7_block(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut texels = [[0; 4]; 16];
+    if block[0] == 0 {
+        return texels;
+    }
+    let mut reader = BitReader::new(block);
+    let mode_index = block[0].trailing_zeros();
+    reader.read(mode_index + 1);
+    let mode = &MODES[mode_index as usize];
+
+    let partition = reader.read(mode.partition_bits) as usize;
+    let rotation = reader.read(mode.rotation_bits);
+    let index_selection = reader.read(mode.index_selection_bits);
+
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for c in 0..3 {
+        for endpoint in endpoints.iter_mut().take(endpoint_count) {
+            endpoint[c] = reader.read(mode.color_bits);
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(endpoint_count) {
+        endpoint[3] = reader.read(mode.alpha_bits);
+    }
+
+    let (mut color_bits, mut alpha_bits) = (mode.color_bits, mode.alpha_bits);
+    if mode.endpoint_pbits || mode.shared_pbits {
+        let mut pbits = [0; 6];
+        if mode.endpoint_pbits {
+            for pbit in pbits.iter_mut().take(endpoint_count) {
+                *pbit = reader.read(1);
+            }
+        } else {
+            for subset in 0..mode.subsets {
+                let pbit = reader.read(1);
+                pbits[subset * 2] = pbit;
+                pbits[subset * 2 + 1] = pbit;
+            }
+        }
+        for (endpoint, pbit) in endpoints.iter_mut().zip(pbits.iter()) {
+            for channel in endpoint.iter_mut() {
+                *channel = (*channel << 1) | pbit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+
+    let mut unquantized = [[0u8; 4]; 6];
+    for (out, endpoint) in unquantized.iter_mut().zip(endpoints.iter()) {
+        for c in 0..3 {
+            out[c] = unquantize(endpoint[c], color_bits);
+        }
+        out[3] = if alpha_bits > 0 {
+            unquantize(endpoint[3], alpha_bits)
+        } else {
+            255
+        };
+    }
+
+    let mut indices = [0; 16];
+    for (i, index) in indices.iter_mut().enumerate() {
+        let anchor = is_anchor(mode.subsets, partition, i);
+        *index = reader.read(mode.index_bits - anchor as u32) as usize;
+    }
+    let mut secondary_indices = [0; 16];
+    if mode.secondary_index_bits > 0 {
+        for (i, index) in secondary_indices.iter_mut().enumerate() {
+            *index = reader.read(mode.secondary_index_bits - (i == 0) as u32) as usize;
+        }
+    }
+
+    let (color_weights, alpha_weights) = if mode.secondary_index_bits == 0 {
+        (weights(mode.index_bits), weights(mode.index_bits))
+    } else if index_selection == 0 {
+        (weights(mode.index_bits), weights(mode.secondary_index_bits))
+    } else {
+        (weights(mode.secondary_index_bits), weights(mode.index_bits))
+    };
+
+    for (i, texel) in texels.iter_mut().enumerate() {
+        let subset = subset(mode.subsets, partition, i);
+        let (e0, e1) = (unquantized[subset * 2], unquantized[subset * 2 + 1]);
+        let (color_index, alpha_index) = if mode.secondary_index_bits == 0 {
+            (indices[i], indices[i])
+        } else if index_selection == 0 {
+            (indices[i], secondary_indices[i])
+        } else {
+            (secondary_indices[i], indices[i])
+        };
+        for c in 0..3 {
+            texel[c] = interpolate(e0[c], e1[c], color_weights[color_index]);
+        }
+        texel[3] = interpolate(e0[3], e1[3], alpha_weights[alpha_index]);
+
+        match rotation {
+            1 => texel.swap(0, 3),
+            2 => texel.swap(1, 3),
+            3 => texel.swap(2, 3),
+            _ => {}
+        }
+    }
+    texels
+}
+
+/// Encodes 16 row-major RGBA texels into a BC7 block.
+fn encode_bc7_block(
+    texels: &[[f32; 4]],
+    settings: &SearchSettings,
+    channel_weights: [f32; 4],
+) -> [u8; 16] {
+    let mut target = [[0.0; 4]; 16];
+    for (out, texel) in target.iter_mut().zip(texels) {
+        *out = texel.map(|v| v.clamp(0.0, 1.0) * 255.0);
+    }
+    let opaque = target.iter().all(|texel| texel[3] >= 254.5);
+    let modes = if opaque {
+        for texel in &mut target {
+            texel[3] = 255.0;
+        }
+        settings.opaque_modes
+    } else {
+        settings.alpha_modes
+    };
+
+    let mut best = Bc7Candidate {
+        error: f32::INFINITY,
+        block: [0; 16],
+    };
+    // Partitioned modes have no rotation, so the ranking of their partitions only depends on
+    // their number of subsets.
+    let mut rankings: [Option<Vec<(f32, usize)>>; 2] = [None, None];
+    for &mode in modes {
+        let searched = |bits: u32, count: usize| match bits {
+            0 => 1,
+            _ if settings.rotations => count,
+            _ => 1,
+        };
+        let rotations = searched(MODES[mode].rotation_bits, 4);
+        let index_selections = searched(MODES[mode].index_selection_bits, 2);
+        for rotation in 0..rotations {
+            for index_selection in 0..index_selections {
+                let fit = ModeFit::new(
+                    mode,
+                    rotation,
+                    index_selection,
+                    &target,
+                    channel_weights,
+                    opaque,
+                    settings,
+                );
+                let partitions = match fit.mode.subsets {
+                    1 => vec![(0.0, 0)],
+                    subsets => rankings[subsets - 2]
+                        .get_or_insert_with(|| fit.rank_partitions())
+                        .iter()
+                        .copied()
+                        .filter(|(_, partition)| *partition < 1 << fit.mode.partition_bits)
+                        .take(settings.partitions)
+                        .collect(),
+                };
+                for (estimate, partition) in partitions {
+                    // The estimate approximates the error of the best unquantized endpoints, so
+                    // later partitions are unlikely to do better.
+                    if estimate >= best.error {
+                        break;
+                    }
+                    fit.encode(partition, &mut best);
+                    if best.error == 0.0 {
+                        return best.block;
+                    }
+                }
+            }
+        }
+    }
+    best.block
+}
+
+struct Bc7Candidate {
+    error: f32,
+    block: [u8; 16],
+}
+
+/// Quantized endpoints and indices of one subset of a block.
+#[derive(Clone, Copy)]
+struct SubsetFit {
+    error: f32,
+    /// Quantized endpoint channels, without their p-bits.
+    endpoints: [[u32; 4]; 2],
+    pbits: [u32; 2],
+    /// Indices of the texels of the subset, by texel.
+    color_indices: [u32; 16],
+    /// Indices of the alpha channel of the texels of the subset, for modes with two index sets.
+    alpha_indices: [u32; 16],
+}
+
+/// Encodes blocks with one mode, rotation and index selection.
+struct ModeFit<'a> {
+    mode_index: usize,
+    mode: &'static Mode,
+    rotation: usize,
+    index_selection: usize,
+    /// Texels in 8-bit units, with the rotation applied.
+    texels: [[f32; 4]; 16],
+    channel_weights: [f32; 4],
+    /// Whether every texel is opaque, in which case modes storing alpha keep it exact.
+    opaque: bool,
+    /// Whether alpha has its own index set.
+    separate_alpha: bool,
+    color_index_bits: u32,
+    alpha_index_bits: u32,
+    settings: &'a SearchSettings,
+}
+
+impl<'a> ModeFit<'a> {
+    fn new(
+        mode_index: usize,
+        rotation: usize,
+        index_selection: usize,
+        texels: &[[f32; 4]; 16],
+        mut channel_weights: [f32; 4],
+        opaque: bool,
+        settings: &'a SearchSettings,
+    ) -> ModeFit<'a> {
+        let mode = &MODES[mode_index];
+        let mut texels = *texels;
+        if rotation > 0 {
+            for texel in &mut texels {
+                texel.swap(rotation - 1, 3);
+            }
+            channel_weights.swap(rotation - 1, 3);
+        }
+        let (color_index_bits, alpha_index_bits) =
+            match (mode.secondary_index_bits, index_selection) {
+                (0, _) => (mode.index_bits, mode.index_bits),
+                (secondary, 0) => (mode.index_bits, secondary),
+                (secondary, _) => (secondary, mode.index_bits),
+            };
+        ModeFit {
+            mode_index,
+            mode,
+            rotation,
+            index_selection,
+            texels,
+            channel_weights,
+            opaque,
+            separate_alpha: mode.secondary_index_bits > 0,
+            color_index_bits,
+            alpha_index_bits,
+            settings,
+        }
+    }
+
+    /// Channels sharing the color index set. Modes without alpha include it, so that it's
+    /// measured against the opaque alpha they decode to.
+    fn color_channels(&self) -> Range<usize> {
+        if self.separate_alpha {
+            0..3
+        } else {
+            0..4
+        }
+    }
+
+    /// Returns every partition of the number of subsets of the mode with its estimated error,
+    /// ordered by that estimate.
+    fn rank_partitions(&self) -> Vec<(f32, usize)> {
+        let mut estimates: Vec<(f32, usize)> = (0..64)
+            .map(|partition| (self.estimate_partition(partition), partition))
+            .collect();
+        estimates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        estimates
+    }
+
+    /// Estimates the error of a partition as the weighted squared distance of the texels of each
+    /// subset to their principal axis, which would bound the error of any endpoints from below if
+    /// the axis were exact.
+    fn estimate_partition(&self, partition: usize) -> f32 {
+        let mut error = 0.0;
+        for subset in 0..self.mode.subsets {
+            let (members, count) = self.members(partition, subset);
+            let (_, mut covariance) = self.covariance(&members[..count], 0..4);
+            for (a, row) in covariance.iter_mut().enumerate() {
+                for (b, value) in row.iter_mut().enumerate() {
+                    *value *= (self.channel_weights[a] * self.channel_weights[b]).sqrt();
+                }
+            }
+            let (_, eigenvalue) = principal_axis(&covariance);
+            let trace: f32 = (0..4).map(|c| covariance[c][c]).sum();
+            error += (trace - eigenvalue).max(0.0);
+        }
+        error
+    }
+
+    fn members(&self, partition: usize, subset_index: usize) -> ([usize; 16], usize) {
+        let mut members = [0; 16];
+        let mut count = 0;
+        for i in 0..16 {
+            if subset(self.mode.subsets, partition, i) == subset_index {
+                members[count] = i;
+                count += 1;
+            }
+        }
+        (members, count)
+    }
+
+    /// Returns the mean and the covariance matrix, not divided by the number of texels, of the
+    /// channels of some texels.
+    fn covariance(&self, members: &[usize], channels: Range<usize>) -> ([f32; 4], [[f32; 4]; 4]) {
+        let mut mean = [0.0; 4];
+        for &i in members {
+            for (sum, value) in mean.iter_mut().zip(self.texels[i]) {
+                *sum += value;
+            }
+        }
+        for value in &mut mean {
+            *value /= members.len().max(1) as f32;
+        }
+        let mut covariance = [[0.0; 4]; 4];
+        for &i in members {
+            let mut centered = [0.0; 4];
+            for c in channels.clone() {
+                centered[c] = self.texels[i][c] - mean[c];
+            }
+            for a in 0..4 {
+                for b in 0..4 {
+                    covariance[a][b] += centered[a] * centered[b];
+                }
+            }
+        }
+        (mean, covariance)
+    }
+
+    /// Encodes a partition, replacing `best` if the result has a lower error.
+    fn encode(&self, partition: usize, best: &mut Bc7Candidate) {
+        let subsets = self.mode.subsets;
+        let mut fits = [None; 3];
+        let mut error = 0.0;
+        for (subset, fit) in fits.iter_mut().enumerate().take(subsets) {
+            let (members, count) = self.members(partition, subset);
+            let mut subset_fit = self.fit_subset(&members[..count]);
+            error += subset_fit.error;
+            if error >= best.error {
+                return;
+            }
+            self.fix_anchor(
+                &mut subset_fit,
+                &members[..count],
+                anchor(subsets, partition, subset),
+            );
+            *fit = Some(subset_fit);
+        }
+        let fits: Vec<SubsetFit> = fits.iter().flatten().copied().collect();
+
+        let mode = self.mode;
+        let mut writer = BitWriter::new();
+        writer.write(self.mode_index as u32 + 1, 1 << self.mode_index);
+        writer.write(mode.partition_bits, partition as u32);
+        writer.write(mode.rotation_bits, self.rotation as u32);
+        writer.write(mode.index_selection_bits, self.index_selection as u32);
+        for c in 0..3 {
+            for fit in &fits {
+                writer.write(mode.color_bits, fit.endpoints[0][c]);
+                writer.write(mode.color_bits, fit.endpoints[1][c]);
+            }
+        }
+        for fit in &fits {
+            writer.write(mode.alpha_bits, fit.endpoints[0][3]);
+            writer.write(mode.alpha_bits, fit.endpoints[1][3]);
+        }
+        for fit in &fits {
+            if mode.endpoint_pbits {
+                writer.write(1, fit.pbits[0]);
+                writer.write(1, fit.pbits[1]);
+            } else if mode.shared_pbits {
+                writer.write(1, fit.pbits[0]);
+            }
+        }
+
+        let mut color_indices = [0; 16];
+        let mut alpha_indices = [0; 16];
+        for (i, (color, alpha)) in color_indices
+            .iter_mut()
+            .zip(alpha_indices.iter_mut())
+            .enumerate()
+        {
+            let fit = &fits[subset(subsets, partition, i)];
+            *color = fit.color_indices[i];
+            *alpha = fit.alpha_indices[i];
+        }
+        let (primary, secondary) = if self.index_selection == 0 {
+            (color_indices, alpha_indices)
+        } else {
+            (alpha_indices, color_indices)
+        };
+        for (i, index) in primary.iter().enumerate() {
+            let anchor = is_anchor(subsets, partition, i);
+            writer.write(mode.index_bits - anchor as u32, *index);
+        }
+        if mode.secondary_index_bits > 0 {
+            for (i, index) in secondary.iter().enumerate() {
+                writer.write(mode.secondary_index_bits - (i == 0) as u32, *index);
+            }
+        }
+        *best = Bc7Candidate {
+            error,
+            block: writer.finish(),
+        };
+    }
+
+    /// Swaps the endpoints of a subset whose anchor index has its highest bit set, which isn't
+    /// stored, inverting its indices.
+    fn fix_anchor(&self, fit: &mut SubsetFit, members: &[usize], anchor: usize) {
+        let sets = [
+            (self.color_channels(), self.color_index_bits, false),
+            (3..4, self.alpha_index_bits, true),
+        ];
+        for (channels, bits, alpha) in sets.iter().cloned() {
+            if alpha && !self.separate_alpha {
+                continue;
+            }
+            let indices = if alpha {
+                &mut fit.alpha_indices
+            } else {
+                &mut fit.color_indices
+            };
+            if indices[anchor] >> (bits - 1) == 0 {
+                continue;
+            }
+            for &i in members {
+                indices[i] = (1 << bits) - 1 - indices[i];
+            }
+            for c in channels {
+                let (e0, e1) = (fit.endpoints[0][c], fit.endpoints[1][c]);
+                fit.endpoints[0][c] = e1;
+                fit.endpoints[1][c] = e0;
+            }
+            if !self.separate_alpha {
+                fit.pbits.swap(0, 1);
+            }
+        }
+    }
+
+    /// Fits the endpoints of the texels of a subset to their principal axis, then refines them.
+    fn fit_subset(&self, members: &[usize]) -> SubsetFit {
+        let mut endpoints = [[255.0; 4]; 2];
+        self.fit_line(members, self.color_channels(), &mut endpoints);
+        if self.separate_alpha {
+            self.fit_line(members, 3..4, &mut endpoints);
+        }
+        let mut best = self.quantize_and_evaluate(members, &endpoints);
+        for _ in 0..self.settings.refinements {
+            let endpoints = self.least_squares_endpoints(members, &best);
+            let fit = self.quantize_and_evaluate(members, &endpoints);
+            if fit.error < best.error {
+                best = fit;
+            } else {
+                break;
+            }
+        }
+        best
+    }
+
+    /// Sets the endpoints of some channels to the extremes of the projection of the texels onto
+    /// their principal axis.
+    fn fit_line(&self, members: &[usize], channels: Range<usize>, endpoints: &mut [[f32; 4]; 2]) {
+        let (mean, covariance) = self.covariance(members, channels.clone());
+        let (axis, _) = principal_axis(&covariance);
+        let (mut min, mut max) = (0.0f32, 0.0f32);
+        for &i in members {
+            let t: f32 = channels
+                .clone()
+                .map(|c| (self.texels[i][c] - mean[c]) * axis[c])
+                .sum();
+            min = min.min(t);
+            max = max.max(t);
+        }
+        for c in channels {
+            endpoints[0][c] = (mean[c] + axis[c] * min).clamp(0.0, 255.0);
+            endpoints[1][c] = (mean[c] + axis[c] * max).clamp(0.0, 255.0);
+        }
+    }
+
+    /// Solves for the endpoints minimizing the squared error of the texels with the indices of a
+    /// fit, keeping the endpoints of the fit for index sets where every index is the same.
+    fn least_squares_endpoints(&self, members: &[usize], fit: &SubsetFit) -> [[f32; 4]; 2] {
+        let mut endpoints = [[0.0; 4]; 2];
+        for (out, (endpoint, pbit)) in endpoints
+            .iter_mut()
+            .zip(fit.endpoints.iter().zip(fit.pbits.iter()))
+        {
+            *out = self.unquantize(endpoint, *pbit).map(f32::from);
+        }
+
+        let mut sets = vec![(
+            self.color_channels(),
+            self.color_index_bits,
+            &fit.color_indices,
+        )];
+        if self.separate_alpha {
+            sets.push((3..4, self.alpha_index_bits, &fit.alpha_indices));
+        }
+        for (channels, bits, indices) in sets {
+            let weights = weights(bits);
+            let (mut aa, mut ab, mut bb) = (0.0, 0.0, 0.0);
+            let (mut av, mut bv) = ([0.0; 4], [0.0; 4]);
+            for &i in members {
+                let t = weights[indices[i] as usize] as f32 / 64.0;
+                let (a, b) = (1.0 - t, t);
+                aa += a * a;
+                ab += a * b;
+                bb += b * b;
+                for c in channels.clone() {
+                    av[c] += a * self.texels[i][c];
+                    bv[c] += b * self.texels[i][c];
+                }
+            }
+            let det = aa * bb - ab * ab;
+            if det.abs() < 1e-6 {
+                continue;
+            }
+            for c in channels {
+                endpoints[0][c] = ((bb * av[c] - ab * bv[c]) / det).clamp(0.0, 255.0);
+                endpoints[1][c] = ((aa * bv[c] - ab * av[c]) / det).clamp(0.0, 255.0);
+            }
+        }
+        endpoints
+    }
+
+    /// Quantizes endpoints in 8-bit units and assigns the indices of the texels of a subset,
+    /// searching the p-bits if the settings allow it.
+    fn quantize_and_evaluate(&self, members: &[usize], endpoints: &[[f32; 4]; 2]) -> SubsetFit {
+        let combinations: &[[u32; 2]] = if self.opaque && self.mode.alpha_bits > 0 {
+            // Only p-bits of 1 represent an alpha of 255 exactly, which also spares the
+            // search.
+            if self.mode.endpoint_pbits || self.mode.shared_pbits {
+                &[[1, 1]]
+            } else {
+                &[[0, 0]]
+            }
+        } else if self.mode.endpoint_pbits {
+            &[[0, 0], [0, 1], [1, 0], [1, 1]]
+        } else if self.mode.shared_pbits {
+            &[[0, 0], [1, 1]]
+        } else {
+            &[[0, 0]]
+        };
+        if self.settings.pbit_search {
+            combinations
+                .iter()
+                .map(|pbits| self.evaluate(members, self.quantize(endpoints, *pbits), *pbits))
+                .min_by(|a, b| a.error.total_cmp(&b.error))
+                .unwrap()
+        } else {
+            let pbits = combinations
+                .iter()
+                .min_by(|a, b| {
+                    let a = self.quantization_error(endpoints, **a);
+                    let b = self.quantization_error(endpoints, **b);
+                    a.total_cmp(&b)
+                })
+                .unwrap();
+            self.evaluate(members, self.quantize(endpoints, *pbits), *pbits)
+        }
+    }
+
+    fn quantization_error(&self, endpoints: &[[f32; 4]; 2], pbits: [u32; 2]) -> f32 {
+        let quantized = self.quantize(endpoints, pbits);
+        let mut error = 0.0;
+        for e in 0..2 {
+            let unquantized = self.unquantize(&quantized[e], pbits[e]);
+            for c in 0..4 {
+                error +=
+                    self.channel_weights[c] * (unquantized[c] as f32 - endpoints[e][c]).powi(2);
+            }
+        }
+        error
+    }
+
+    /// Quantizes endpoints in 8-bit units to the precision of the mode, with the given p-bits.
+    fn quantize(&self, endpoints: &[[f32; 4]; 2], pbits: [u32; 2]) -> [[u32; 4]; 2] {
+        let has_pbits = self.mode.endpoint_pbits || self.mode.shared_pbits;
+        let mut quantized = [[0; 4]; 2];
+        for e in 0..2 {
+            for c in 0..4 {
+                let bits = if c < 3 {
+                    self.mode.color_bits
+                } else {
+                    self.mode.alpha_bits
+                };
+                if bits > 0 {
+                    let pbit = if has_pbits { Some(pbits[e]) } else { None };
+                    quantized[e][c] = quantize_channel(endpoints[e][c], bits, pbit);
+                }
+            }
+        }
+        quantized
+    }
+
+    fn unquantize(&self, endpoint: &[u32; 4], pbit: u32) -> [u8; 4] {
+        let has_pbits = self.mode.endpoint_pbits || self.mode.shared_pbits;
+        let mut unquantized = [255; 4];
+        for (c, out) in unquantized.iter_mut().enumerate() {
+            let bits = if c < 3 {
+                self.mode.color_bits
+            } else {
+                self.mode.alpha_bits
+            };
+            if bits == 0 {
+                continue;
+            }
+            *out = if has_pbits {
+                unquantize((endpoint[c] << 1) | pbit, bits + 1)
+            } else {
+                unquantize(endpoint[c], bits)
+            };
+        }
+        unquantized
+    }
+
+    /// Assigns the texels of a subset the indices closest to them with quantized endpoints.
+    fn evaluate(&self, members: &[usize], endpoints: [[u32; 4]; 2], pbits: [u32; 2]) -> SubsetFit {
+        let unquantized = [
+            self.unquantize(&endpoints[0], pbits[0]),
+            self.unquantize(&endpoints[1], pbits[1]),
+        ];
+        let mut fit = SubsetFit {
+            error: 0.0,
+            endpoints,
+            pbits,
+            color_indices: [0; 16],
+            alpha_indices: [0; 16],
+        };
+        fit.error += self.assign_indices(
+            members,
+            &unquantized,
+            self.color_channels(),
+            self.color_index_bits,
+            &mut fit.color_indices,
+        );
+        if self.separate_alpha {
+            fit.error += self.assign_indices(
+                members,
+                &unquantized,
+                3..4,
+                self.alpha_index_bits,
+                &mut fit.alpha_indices,
+            );
+        }
+        fit
+    }
+
+    fn assign_indices(
+        &self,
+        members: &[usize],
+        endpoints: &[[u8; 4]; 2],
+        channels: Range<usize>,
+        bits: u32,
+        indices: &mut [u32; 16],
+    ) -> f32 {
+        let weights = weights(bits);
+        let mut channel_weights = [0.0; 4];
+        let mut palette = [[0.0; 4]; 16];
+        for c in channels {
+            channel_weights[c] = self.channel_weights[c];
+            for (entry, weight) in palette.iter_mut().zip(weights) {
+                entry[c] = interpolate(endpoints[0][c], endpoints[1][c], *weight) as f32;
+            }
+        }
+        let mut error = 0.0;
+        for &i in members {
+            let texel = &self.texels[i];
+            let mut best = (0, f32::INFINITY);
+            for (index, entry) in palette[..weights.len()].iter().enumerate() {
+                let mut entry_error = 0.0;
+                for c in 0..4 {
+                    entry_error += channel_weights[c] * (texel[c] - entry[c]).powi(2);
+                }
+                if entry_error < best.1 {
+                    best = (index, entry_error);
+                }
+            }
+            indices[i] = best.0 as u32;
+            error += best.1;
+        }
+        error
+    }
+}
+
+/// Returns the unit eigenvector with the largest eigenvalue of a covariance matrix, and that
+/// eigenvalue, by power iteration.
+fn principal_axis(covariance: &[[f32; 4]; 4]) -> ([f32; 4], f32) {
+    // Starting from the row of the channel with the largest variance avoids starting orthogonal
+    // to the axis.
+    let start = (0..4)
+        .max_by(|a, b| covariance[*a][*a].total_cmp(&covariance[*b][*b]))
+        .unwrap();
+    let mut axis = covariance[start];
+    let mut eigenvalue = 0.0;
+    for _ in 0..8 {
+        let length = axis.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length < 1e-6 {
+            return ([0.0; 4], 0.0);
+        }
+        let mut next = [0.0; 4];
+        for (out, row) in next.iter_mut().zip(covariance) {
+            *out = (0..4).map(|c| row[c] * axis[c]).sum::<f32>() / length;
+        }
+        eigenvalue = (0..4).map(|c| next[c] * axis[c]).sum::<f32>() / length;
+        axis = next;
+    }
+    let length = axis.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if length < 1e-6 {
+        return ([0.0; 4], 0.0);
+    }
+    (axis.map(|v| v / length), eigenvalue)
+}
+
+/// Quantizes a channel in 8-bit units to `bits`, not counting a p-bit appended to the result.
+fn quantize_channel(value: f32, bits: u32, pbit: Option<u32>) -> u32 {
+    let max = (1i32 << bits) - 1;
+    let estimate = match pbit {
+        Some(pbit) => (value * ((1 << (bits + 1)) - 1) as f32 / 255.0 - pbit as f32) / 2.0,
+        None => value * max as f32 / 255.0,
+    };
+    let (low, high) = (estimate.floor() as i32 - 1, estimate.ceil() as i32 + 1);
+    (low.max(0)..=high.min(max))
+        .map(|q| q as u32)
+        .min_by(|a, b| {
+            let unquantized = |q: u32| match pbit {
+                Some(pbit) => unquantize((q << 1) | pbit, bits + 1),
+                None => unquantize(q, bits),
+            };
+            let a = (unquantized(*a) as f32 - value).abs();
+            let b = (unquantized(*b) as f32 - value).abs();
+            a.total_cmp(&b)
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One block per mode, checked against an independent decoder. Each block uses a nonzero
+    // partition, and the mode 4 and 5 blocks a nonzero rotation; the mode 4 block also selects
+    // the 3-bit indices for color.
+    const BLOCKS: [([u8; 16], [[u8; 4]; 16]); 8] = [
+        (
+            [
+                0x35, 0xe2, 0x49, 0x93, 0xc1, 0x6f, 0x65, 0x91, 0xb3, 0xac, 0xc9, 0xe5, 0x7e, 0x5d,
+                0xfe, 0xfc,
+            ],
+            [
+                [23, 177, 181, 255],
+                [17, 29, 140, 255],
+                [18, 58, 148, 255],
+                [19, 87, 156, 255],
+                [179, 190, 185, 255],
+                [74, 123, 156, 255],
+                [179, 190, 185, 255],
+                [125, 156, 170, 255],
+                [150, 54, 97, 255],
+                [153, 75, 94, 255],
+                [155, 95, 92, 255],
+                [148, 33, 99, 255],
+                [148, 33, 99, 255],
+                [155, 95, 92, 255],
+                [148, 33, 99, 255],
+                [158, 119, 89, 255],
+            ],
+        ),
+        (
+            [
+                0xda, 0x3a, 0xd1, 0x55, 0xe1, 0x34, 0xe7, 0x98, 0x3d, 0xeb, 0x66, 0x09, 0x1c, 0xad,
+                0xd1, 0x48,
+            ],
+            [
+                [202, 125, 113, 255],
+                [91, 228, 231, 255],
+                [109, 214, 215, 255],
+                [108, 100, 166, 255],
+                [233, 133, 96, 255],
+                [233, 133, 96, 255],
+                [86, 231, 235, 255],
+                [118, 207, 207, 255],
+                [95, 224, 227, 255],
+                [77, 92, 183, 255],
+                [47, 84, 200, 255],
+                [118, 207, 207, 255],
+                [95, 224, 227, 255],
+                [114, 210, 211, 255],
+                [172, 117, 130, 255],
+                [172, 117, 130, 255],
+            ],
+        ),
+        (
+            [
+                0x24, 0xf8, 0x72, 0x0e, 0x70, 0xfe, 0x2a, 0x3e, 0x80, 0xf2, 0x8a, 0x08, 0x4f, 0xff,
+                0xf1, 0xa2,
+            ],
+            [
+                [185, 196, 187, 255],
+                [231, 231, 165, 255],
+                [185, 196, 187, 255],
+                [90, 123, 231, 255],
+                [90, 123, 231, 255],
+                [90, 123, 231, 255],
+                [90, 123, 231, 255],
+                [185, 196, 187, 255],
+                [115, 173, 173, 255],
+                [115, 190, 101, 255],
+                [231, 0, 231, 255],
+                [76, 38, 98, 255],
+                [115, 181, 138, 255],
+                [115, 173, 173, 255],
+                [76, 38, 98, 255],
+                [76, 38, 98, 255],
+            ],
+        ),
+        (
+            [
+                0x48, 0x2c, 0xdd, 0x84, 0x21, 0x26, 0x46, 0xe9, 0xa5, 0x8c, 0x4c, 0x5e, 0x81, 0xdc,
+                0x91, 0x52,
+            ],
+            [
+                [151, 49, 83, 255],
+                [151, 49, 83, 255],
+                [151, 49, 83, 255],
+                [174, 65, 102, 255],
+                [197, 82, 121, 255],
+                [220, 98, 140, 255],
+                [197, 82, 121, 255],
+                [134, 122, 120, 255],
+                [151, 49, 83, 255],
+                [197, 82, 121, 255],
+                [151, 49, 83, 255],
+                [50, 68, 142, 255],
+                [174, 65, 102, 255],
+                [197, 82, 121, 255],
+                [93, 95, 131, 255],
+                [9, 41, 153, 255],
+            ],
+        ),
+        (
+            [
+                0xf0, 0x63, 0x03, 0x77, 0x10, 0xc1, 0x07, 0x10, 0x5d, 0xe2, 0x79, 0xb6, 0xda, 0xaa,
+                0xba, 0xb8,
+            ],
+            [
+                [24, 0, 90, 57],
+                [222, 115, 16, 66],
+                [52, 16, 16, 58],
+                [108, 49, 16, 61],
+                [108, 49, 16, 61],
+                [166, 83, 169, 63],
+                [194, 99, 16, 65],
+                [194, 99, 169, 65],
+                [80, 32, 169, 60],
+                [166, 83, 243, 63],
+                [80, 32, 169, 60],
+                [166, 83, 16, 63],
+                [108, 49, 90, 61],
+                [52, 16, 16, 58],
+                [194, 99, 243, 65],
+                [166, 83, 243, 63],
+            ],
+        ),
+        (
+            [
+                0xa0, 0x72, 0xe6, 0xbd, 0xb3, 0x52, 0x17, 0x3a, 0x17, 0xc4, 0xb6, 0xf8, 0x93, 0x4b,
+                0x45, 0x39,
+            ],
+            [
+                [204, 157, 128, 180],
+                [178, 133, 171, 117],
+                [229, 157, 86, 239],
+                [229, 182, 86, 239],
+                [178, 206, 171, 117],
+                [229, 182, 86, 239],
+                [178, 133, 171, 117],
+                [204, 157, 128, 180],
+                [153, 157, 213, 58],
+                [178, 157, 171, 117],
+                [204, 133, 128, 180],
+                [204, 157, 128, 180],
+                [229, 157, 86, 239],
+                [153, 182, 213, 58],
+                [153, 206, 213, 58],
+                [153, 133, 213, 58],
+            ],
+        ),
+        (
+            [
+                0xc0, 0x99, 0x46, 0x3f, 0x95, 0xf3, 0x9a, 0x47, 0x0e, 0x90, 0x93, 0x7b, 0x48, 0x33,
+                0x68, 0x73,
+            ],
+            [
+                [79, 207, 177, 148],
+                [102, 244, 228, 154],
+                [102, 244, 228, 154],
+                [72, 198, 164, 147],
+                [92, 228, 206, 152],
+                [72, 198, 164, 147],
+                [65, 187, 149, 145],
+                [79, 207, 177, 148],
+                [75, 203, 171, 148],
+                [89, 223, 199, 151],
+                [92, 228, 206, 152],
+                [92, 228, 206, 152],
+                [75, 203, 171, 148],
+                [82, 212, 184, 149],
+                [92, 228, 206, 152],
+                [79, 207, 177, 148],
+            ],
+        ),
+        (
+            [
+                0x80, 0x39, 0x62, 0xbd, 0xf3, 0x15, 0x65, 0x95, 0x22, 0xb6, 0x10, 0x24, 0x93, 0x6d,
+                0x65, 0xcf,
+            ],
+            [
+                [65, 227, 170, 105],
+                [239, 147, 102, 105],
+                [239, 142, 20, 12],
+                [97, 89, 146, 8],
+                [87, 134, 154, 40],
+                [76, 182, 162, 73],
+                [239, 150, 142, 150],
+                [239, 147, 102, 105],
+                [87, 134, 154, 40],
+                [65, 227, 170, 105],
+                [239, 150, 142, 150],
+                [239, 147, 102, 105],
+                [239, 150, 142, 150],
+                [76, 182, 162, 73],
+                [87, 134, 154, 40],
+                [239, 145, 60, 57],
+            ],
+        ),
+    ];
+
+    #[test]
+    fn decodes_every_mode() {
+        for (mode, (block, expected)) in BLOCKS.iter().enumerate() {
+            assert_eq!(decode_bc7_block(block), *expected, "mode {}", mode);
+        }
+    }
+
+    /// A 16x16 gradient with deterministic noise, as RGBA8, with a gradient of alpha if `alpha`.
+    fn test_image(alpha: bool) -> Vec<u8> {
+        let mut state = 0x2545_F491u32;
+        let mut rgba = Vec::new();
+        for y in 0..16 {
+            for x in 0..16 {
+                let mut noise = || {
+                    state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    (state >> 24) as i32 % 12 - 6
+                };
+                let r = x * 15 + noise();
+                let g = y * 15 + noise();
+                let b = 255 - x * 8 - y * 7 + noise();
+                let a = if alpha { (x + y) * 8 + noise() } else { 255 };
+                for value in [r, g, b, a] {
+                    rgba.push(value.clamp(0, 255) as u8);
+                }
+            }
+        }
+        rgba
+    }
+
+    const PROFILES: [Bc7Profile; 5] = [
+        Bc7Profile::UltraFast,
+        Bc7Profile::VeryFast,
+        Bc7Profile::Fast,
+        Bc7Profile::Basic,
+        Bc7Profile::Slow,
+    ];
+
+    fn round_trip(rgba: &[u8], profile: Bc7Profile) -> (Vec<u8>, Vec<u8>) {
+        let surface = SurfaceDescriptor::new(16, 16);
+        let blocks = Bc7Encoder::new(BlockCompressionType::UnsignedNormalized)
+            .profile(profile)
+            .encode(
+                EncoderInput::Rgba8(rgba),
+                surface,
+                EncoderOptions::default(),
+            )
+            .unwrap();
+        let decoded = Bc7Decoder::new(BlockCompressionType::UnsignedNormalized)
+            .decode(&blocks, surface)
+            .unwrap();
+        (blocks, decoded)
+    }
+
+    fn rmse(a: &[u8], b: &[u8]) -> f32 {
+        let error: i32 = a
+            .iter()
+            .zip(b)
+            .map(|(&a, &b)| (a as i32 - b as i32).pow(2))
+            .sum();
+        (error as f32 / a.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn round_trips_every_profile() {
+        let bounds = [
+            (false, [9.5, 5.0, 5.0, 4.0, 4.0]),
+            (true, [9.5, 9.5, 6.0, 5.5, 5.5]),
+        ];
+        for &(alpha, bounds) in &bounds {
+            let rgba = test_image(alpha);
+            for (&profile, &bound) in PROFILES.iter().zip(&bounds) {
+                let (_, decoded) = round_trip(&rgba, profile);
+                let rmse = rmse(&rgba, &decoded);
+                assert!(
+                    rmse <= bound,
+                    "{:?} rmse {} with alpha {}",
+                    profile,
+                    rmse,
+                    alpha
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn opaque_blocks_decode_opaque() {
+        let rgba = test_image(false);
+        for &profile in &PROFILES {
+            let (_, decoded) = round_trip(&rgba, profile);
+            assert!(decoded.chunks_exact(4).all(|texel| texel[3] == 255));
+        }
+    }
+
+    #[test]
+    fn profiles_only_use_their_modes() {
+        for &alpha in &[false, true] {
+            let rgba = test_image(alpha);
+            for &profile in &PROFILES {
+                let settings = profile.settings();
+                let modes = if alpha {
+                    settings.alpha_modes
+                } else {
+                    settings.opaque_modes
+                };
+                let (blocks, _) = round_trip(&rgba, profile);
+                for block in blocks.chunks_exact(16) {
+                    let mode = block[0].trailing_zeros() as usize;
+                    assert!(modes.contains(&mode), "mode {} in {:?}", mode, profile);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reserved_mode_decodes_to_zero() {
+        assert_eq!(decode_bc7_block(&[0; 16]), [[0; 4]; 16]);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{check_len, BlockLayout, DecodedBlock, Decoder, DecoderResult, SurfaceDescriptor};
+
+/// Iterator over the encoded blocks of a surface, returned by [`Decoder::blocks`].
+///
+/// Blocks are visited in row-major order, one depth slice after another. Blocks are not decoded
+/// until [`Block::decode`] is called, so the raw bytes of each block can be inspected cheaply.
+#[derive(Clone)]
+pub struct Blocks<'a> {
+    decoder: &'a (dyn Decoder + Sync),
+    bytes: &'a [u8],
+    surface: SurfaceDescriptor,
+    layout: BlockLayout,
+    row_pitch: usize,
+    slice_pitch: usize,
+    columns: usize,
+    rows: usize,
+    front: usize,
+    back: usize,
+}
+
+impl<'a> Blocks<'a> {
+    pub(crate) fn new(
+        decoder: &'a (dyn Decoder + Sync),
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+        layout: BlockLayout,
+    ) -> DecoderResult<Blocks<'a>> {
+        check_len(bytes, layout.surface_bytes(surface)?)?;
+        let (row_pitch, slice_pitch) = layout.pitches(surface)?;
+        let (columns, rows) = layout.block_count(surface.width, surface.height);
+        Ok(Blocks {
+            decoder,
+            bytes,
+            surface,
+            layout,
+            row_pitch,
+            slice_pitch,
+            columns,
+            rows,
+            front: 0,
+            back: columns * rows * surface.depth,
+        })
+    }
+
+    /// Number of blocks covering the surface, as `(columns, rows)`.
+    pub fn block_count(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    /// Returns the `index`th block in iteration order, which must be in bounds.
+    fn block(&self, index: usize) -> Block<'a> {
+        let (x, y) = (index % self.columns, (index / self.columns) % self.rows);
+        let z = index / (self.columns * self.rows);
+        let offset = z * self.slice_pitch + y * self.row_pitch + x * self.layout.bytes;
+        Block {
+            decoder: self.decoder,
+            slice_bytes: &self.bytes[z * self.slice_pitch..],
+            surface: SurfaceDescriptor {
+                depth: 1,
+                ..self.surface
+            },
+            layout: self.layout,
+            coords: (x, y),
+            slice: z,
+            bytes: &self.bytes[offset..offset + self.layout.bytes],
+        }
+    }
+
+    /// Converts the iterator into a parallel iterator over the same blocks.
+    #[cfg(feature = "rayon")]
+    pub fn into_par_iter(self) -> impl rayon::iter::IndexedParallelIterator<Item = Block<'a>> + 'a {
+        use rayon::iter::{IntoParallelIterator, ParallelIterator};
+        let range = self.front..self.back;
+        range.into_par_iter().map(move |index| self.block(index))
+    }
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Block<'a>;
+
+    fn next(&mut self) -> Option<Block<'a>> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(self.block(self.front - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Block<'a>> {
+        self.front = self.front.saturating_add(n).min(self.back);
+        self.next()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Blocks<'a> {
+    fn next_back(&mut self) -> Option<Block<'a>> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.block(self.back))
+    }
+}
+
+impl<'a> ExactSizeIterator for Blocks<'a> {}
+
+impl<'a> std::iter::FusedIterator for Blocks<'a> {}
+
+/// A single encoded block of a surface.
+#[derive(Clone)]
+pub struct Block<'a> {
+    decoder: &'a (dyn Decoder + Sync),
+    slice_bytes: &'a [u8],
+    surface: SurfaceDescriptor,
+    layout: BlockLayout,
+    coords: (usize, usize),
+    slice: usize,
+    bytes: &'a [u8],
+}
+
+impl<'a> Block<'a> {
+    /// Position of the block in blocks, as `(bx, by)`.
+    pub fn coords(&self) -> (usize, usize) {
+        self.coords
+    }
+
+    /// Depth slice containing the block.
+    pub fn slice(&self) -> usize {
+        self.slice
+    }
+
+    /// Position of the top left texel of the block in pixels.
+    pub fn origin(&self) -> (usize, usize) {
+        (
+            self.coords.0 * self.layout.width,
+            self.coords.1 * self.layout.height,
+        )
+    }
+
+    /// Size of the block in texels, as `(width, height)`.
+    pub fn size(&self) -> (usize, usize) {
+        (self.layout.width, self.layout.height)
+    }
+
+    /// Encoded bytes of the block.
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Decodes the block with the decoder that produced it.
+    pub fn decode(&self) -> DecoderResult<DecodedBlock> {
+        self.decoder
+            .decode_at_pixel(self.slice_bytes, self.surface, self.origin())
+    }
+}
+
+impl<'a> std::fmt::Debug for Block<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Block")
+            .field("coords", &self.coords)
+            .field("slice", &self.slice)
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bc1Decoder;
+    use ddsfmt::BlockCompressionType;
+
+    /// A 9x5 volume of 3x2 solid BC1 blocks per slice, each of a different color, with 8 bytes of
+    /// padding between the slices.
+    fn volume() -> (Vec<u8>, SurfaceDescriptor) {
+        let mut bytes = Vec::new();
+        for i in 0..12u16 {
+            let [lo, hi] = (i * 0x0841).to_le_bytes();
+            bytes.extend_from_slice(&[lo, hi, lo, hi, 0, 0, 0, 0]);
+            if i == 5 {
+                bytes.extend_from_slice(&[0xEE; 8]);
+            }
+        }
+        let surface = SurfaceDescriptor {
+            depth: 2,
+            slice_pitch: Some(56),
+            ..SurfaceDescriptor::new(9, 5)
+        };
+        (bytes, surface)
+    }
+
+    fn decoder() -> Bc1Decoder {
+        Bc1Decoder::new(BlockCompressionType::UnsignedNormalized)
+    }
+
+    #[test]
+    fn visits_blocks_in_row_major_order_by_slice() {
+        let (bytes, surface) = volume();
+        let decoder = decoder();
+        let blocks = decoder.blocks(&bytes, surface).unwrap();
+        assert_eq!(blocks.block_count(), (3, 2));
+
+        let visited: Vec<_> = blocks
+            .map(|block| {
+                (
+                    block.slice(),
+                    block.coords(),
+                    block.origin(),
+                    block.bytes()[0],
+                )
+            })
+            .collect();
+        let expected: Vec<_> = (0..12usize)
+            .map(|i| {
+                let (x, y) = (i % 3, (i / 3) % 2);
+                (i / 6, (x, y), (x * 4, y * 4), (i as u16 * 0x0841) as u8)
+            })
+            .collect();
+        assert_eq!(visited, expected);
+    }
+
+    #[test]
+    fn counts_blocks_from_both_ends() {
+        let (bytes, surface) = volume();
+        let decoder = decoder();
+        let mut blocks = decoder.blocks(&bytes, surface).unwrap();
+        assert_eq!(blocks.len(), 12);
+
+        let last = blocks.next_back().unwrap();
+        assert_eq!((last.slice(), last.coords()), (1, (2, 1)));
+        assert_eq!(blocks.len(), 11);
+        let first = blocks.next().unwrap();
+        assert_eq!((first.slice(), first.coords()), (0, (0, 0)));
+        assert_eq!(blocks.len(), 10);
+
+        let block = blocks.nth(5).unwrap();
+        assert_eq!((block.slice(), block.coords()), (1, (0, 0)));
+        assert_eq!(blocks.len(), 4);
+        assert!(blocks.nth(4).is_none());
+        assert_eq!(blocks.len(), 0);
+        assert!(blocks.next_back().is_none());
+    }
+
+    #[test]
+    fn decodes_blocks_like_the_surface() {
+        let (bytes, surface) = volume();
+        let decoder = decoder();
+        let texels = decoder.decode(&bytes, surface).unwrap();
+        for block in decoder.blocks(&bytes, surface).unwrap() {
+            let decoded = block.decode().unwrap();
+            assert_eq!(decoded.origin(), block.origin());
+            let (width, height) = decoded.valid_size();
+            for y in 0..height {
+                for x in 0..width {
+                    let (px, py) = (block.origin().0 + x, block.origin().1 + y);
+                    let offset = ((block.slice() * 5 + py) * 9 + px) * 4;
+                    assert_eq!(decoded.texel(x, y)[..], texels[offset..offset + 4]);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn decodes_in_parallel_like_serially() {
+        use rayon::iter::ParallelIterator;
+
+        let (bytes, surface) = volume();
+        let decoder = decoder();
+        let serial: Vec<_> = decoder
+            .blocks(&bytes, surface)
+            .unwrap()
+            .map(|block| (block.slice(), block.coords(), block.decode().unwrap()))
+            .collect();
+        let parallel: Vec<_> = decoder
+            .blocks(&bytes, surface)
+            .unwrap()
+            .into_par_iter()
+            .map(|block| (block.slice(), block.coords(), block.decode().unwrap()))
+            .collect();
+        assert_eq!(parallel, serial);
+    }
+}
@@ -0,0 +1,144 @@
+//! Definitions shared by the BPTC formats, BC6H and BC7.
+
+use byteorder::{ByteOrder, LE};
+
+/// Reads little-endian bit fields from a 128-bit block, starting at the least significant bit.
+pub(crate) struct BitReader {
+    bits: u128,
+}
+
+impl BitReader {
+    pub fn new(block: &[u8]) -> BitReader {
+        BitReader {
+            bits: LE::read_u128(block),
+        }
+    }
+
+    pub fn read(&mut self, count: u32) -> u32 {
+        let value = (self.bits & ((1 << count) - 1)) as u32;
+        self.bits >>= count;
+        value
+    }
+}
+
+/// Writes little-endian bit fields to a 128-bit block, mirroring [`BitReader`].
+pub(crate) struct BitWriter {
+    bits: u128,
+    position: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> BitWriter {
+        BitWriter {
+            bits: 0,
+            position: 0,
+        }
+    }
+
+    pub fn write(&mut self, count: u32, value: u32) {
+        debug_assert!(count == 32 || value >> count == 0);
+        self.bits |= (value as u128) << self.position;
+        self.position += count;
+    }
+
+    pub fn finish(self) -> [u8; 16] {
+        debug_assert_eq!(self.position, 128);
+        self.bits.to_le_bytes()
+    }
+}
+
+/// Interpolation weights for 2-bit indices.
+pub(crate) const WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+/// Interpolation weights for 3-bit indices.
+pub(crate) const WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+/// Interpolation weights for 4-bit indices.
+pub(crate) const WEIGHTS_4: [u32; 16] =
+    [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+/// Returns the interpolation weights for indices of the given bit width.
+pub(crate) fn weights(index_bits: u32) -> &'static [u32] {
+    match index_bits {
+        2 => &WEIGHTS_2,
+        3 => &WEIGHTS_3,
+        _ => &WEIGHTS_4,
+    }
+}
+
+/// Two-subset partitions, one bit per texel selecting the subset, texel 0 in the lowest bit.
+///
+/// BC6H only uses the first 32 partitions.
+pub(crate) const PARTITIONS_2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80, 0xC800, 0xFFEC, 0xFE80, 0xE800,
+    0xFFE8, 0xFF00, 0xFFF0, 0xF000, 0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C, 0xAAAA, 0xF0F0, 0x5A5A, 0x33CC,
+    0x3C3C, 0x55AA, 0x9696, 0xA55A, 0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C, 0x9336, 0x9CC6, 0x817E, 0xE718,
+    0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// Anchor texel of the second subset of each two-subset partition. The anchor of the first
+/// subset is always texel 0.
+pub(crate) const ANCHORS_2: [usize; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 2, 8, 2, 2, 8, 8, 15, 2, 8,
+    2, 2, 8, 8, 2, 2, 15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6, 6, 2, 6, 8, 15, 15, 2,
+    2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Returns the subset of texel `i` in a two-subset partition.
+pub(crate) fn subset_2(partition: usize, i: usize) -> usize {
+    ((PARTITIONS_2[partition] >> i) & 1) as usize
+}
+
+/// Three-subset partitions, two bits per texel selecting the subset, texel 0 in the lowest bits.
+pub(crate) const PARTITIONS_3: [u32; 64] = [
+    0xAA685050, 0x6A5A5040, 0x5A5A4200, 0x5450A0A8, 0xA5A50000, 0xA0A05050, 0x5555A0A0, 0x5A5A5050,
+    0xAA550000, 0xAA555500, 0xAAAA5500, 0x90909090, 0x94949494, 0xA4A4A4A4, 0xA9A59450, 0x2A0A4250,
+    0xA5945040, 0x0A425054, 0xA5A5A500, 0x55A0A0A0, 0xA8A85454, 0x6A6A4040, 0xA4A45000, 0x1A1A0500,
+    0x0050A4A4, 0xAAA59090, 0x14696914, 0x69691400, 0xA08585A0, 0xAA821414, 0x50A4A450, 0x6A5A0200,
+    0xA9A58000, 0x5090A0A8, 0xA8A09050, 0x24242424, 0x00AA5500, 0x24924924, 0x24499224, 0x50A50A50,
+    0x500AA550, 0xAAAA4444, 0x66660000, 0xA5A0A5A0, 0x50A050A0, 0x69286928, 0x44AAAA44, 0x66666600,
+    0xAA444444, 0x54A854A8, 0x95809580, 0x96969600, 0xA85454A8, 0x80959580, 0xAA141414, 0x96960000,
+    0xAAAA1414, 0xA05050A0, 0xA0A5A5A0, 0x96000000, 0x40804080, 0xA9A8A9A8, 0xAAAAAA44, 0x2A4A5254,
+];
+
+/// Anchor texel of the second subset of each three-subset partition.
+pub(crate) const ANCHORS_3_SECOND: [usize; 64] = [
+    3, 3, 15, 15, 8, 3, 15, 15, 8, 8, 6, 6, 6, 5, 3, 3, 3, 3, 8, 15, 3, 3, 6, 10, 5, 8, 8, 6, 8, 5,
+    15, 15, 8, 15, 3, 5, 6, 10, 8, 15, 15, 3, 15, 5, 15, 15, 15, 15, 3, 15, 5, 5, 5, 8, 5, 10, 5,
+    10, 8, 13, 15, 12, 3, 3,
+];
+
+/// Anchor texel of the third subset of each three-subset partition.
+pub(crate) const ANCHORS_3_THIRD: [usize; 64] = [
+    15, 8, 8, 3, 15, 15, 3, 8, 15, 15, 15, 15, 15, 15, 15, 8, 15, 8, 15, 3, 15, 8, 15, 8, 3, 15, 6,
+    10, 15, 15, 10, 8, 15, 3, 15, 10, 10, 8, 9, 10, 6, 15, 8, 15, 3, 6, 6, 8, 15, 3, 15, 15, 15,
+    15, 15, 15, 15, 15, 15, 15, 3, 15, 15, 8,
+];
+
+/// Returns the subset of texel `i` in a partition of a block with the given number of subsets.
+pub(crate) fn subset(subsets: usize, partition: usize, i: usize) -> usize {
+    match subsets {
+        2 => subset_2(partition, i),
+        3 => ((PARTITIONS_3[partition] >> (2 * i)) & 0x3) as usize,
+        _ => 0,
+    }
+}
+
+/// Returns the anchor texel of a subset, whose index is stored with one bit less.
+pub(crate) fn anchor(subsets: usize, partition: usize, subset: usize) -> usize {
+    match (subsets, subset) {
+        (_, 0) => 0,
+        (2, _) => ANCHORS_2[partition],
+        (3, 1) => ANCHORS_3_SECOND[partition],
+        _ => ANCHORS_3_THIRD[partition],
+    }
+}
+
+/// Returns whether texel `i` is the anchor of its subset, whose index is stored with one bit less.
+pub(crate) fn is_anchor(subsets: usize, partition: usize, i: usize) -> bool {
+    match subsets {
+        2 => i == 0 || i == ANCHORS_2[partition],
+        3 => i == 0 || i == ANCHORS_3_SECOND[partition] || i == ANCHORS_3_THIRD[partition],
+        _ => i == 0,
+    }
+}
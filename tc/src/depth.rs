@@ -0,0 +1,340 @@
+use crate::{
+    check_len, decode_block_at, decode_block_surface, BlockLayout, Blocks, ChannelType,
+    DecodedBlock, Decoder, DecoderError, DecoderResult, SurfaceDescriptor, TexelFormat,
+};
+use byteorder::{ByteOrder, LE};
+use ddsfmt::{SpecialUncompressedFormat, TextureFormat, UncompressedFormat};
+
+/// Memory layouts shared by depth formats and their typeless views.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum DepthLayout {
+    /// 32-bit float depth, 8-bit stencil and 24 unused bits.
+    D32S8X24,
+    /// 24-bit normalized depth in the low bits and 8-bit stencil in the high bits.
+    D24S8,
+    D32,
+    D16,
+}
+
+impl DepthLayout {
+    fn block(self) -> BlockLayout {
+        match self {
+            DepthLayout::D32S8X24 => BlockLayout::new(1, 1, 8),
+            DepthLayout::D24S8 | DepthLayout::D32 => BlockLayout::new(1, 1, 4),
+            DepthLayout::D16 => BlockLayout::new(1, 1, 2),
+        }
+    }
+
+    fn has_stencil(self) -> bool {
+        matches!(self, DepthLayout::D32S8X24 | DepthLayout::D24S8)
+    }
+
+    fn decode_pixel(self, pixel: &[u8]) -> (f32, u8) {
+        match self {
+            DepthLayout::D32S8X24 => (LE::read_f32(pixel), pixel[4]),
+            DepthLayout::D24S8 => {
+                let packed = LE::read_u32(pixel);
+                (
+                    (packed & 0xFF_FFFF) as f32 / 16_777_215.0,
+                    (packed >> 24) as u8,
+                )
+            }
+            DepthLayout::D32 => (LE::read_f32(pixel), 0),
+            DepthLayout::D16 => (LE::read_u16(pixel) as f32 / 65535.0, 0),
+        }
+    }
+}
+
+/// Decoded depth and stencil of a surface, stored as separate row-major planes with depth slices
+/// one after another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthStencilPlanes {
+    width: usize,
+    height: usize,
+    slices: usize,
+    depth: Vec<f32>,
+    stencil: Option<Vec<u8>>,
+}
+
+impl DepthStencilPlanes {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Number of depth slices of a volume texture, 1 otherwise.
+    pub fn slices(&self) -> usize {
+        self.slices
+    }
+
+    /// Depth of each pixel. Normalized formats are in `[0, 1]`, float formats are unchanged.
+    pub fn depth(&self) -> &[f32] {
+        &self.depth
+    }
+
+    /// Stencil of each pixel, if the format stores one.
+    pub fn stencil(&self) -> Option<&[u8]> {
+        self.stencil.as_deref()
+    }
+
+    /// The smallest and largest finite depth, or `None` if there are none.
+    pub fn depth_range(&self) -> Option<(f32, f32)> {
+        self.depth
+            .iter()
+            .filter(|d| d.is_finite())
+            .fold(None, |range, &d| match range {
+                None => Some((d, d)),
+                Some((min, max)) => Some((min.min(d), max.max(d))),
+            })
+    }
+
+    /// Remaps depth to an 8-bit grayscale image, with `near` becoming black and `far` white.
+    ///
+    /// Depths outside of the range are clamped, so swapping `near` and `far` displays reversed
+    /// depth buffers. NaNs become black.
+    pub fn to_grayscale(&self, near: f32, far: f32) -> Vec<u8> {
+        let scale = if far != near { 1.0 / (far - near) } else { 0.0 };
+        self.depth
+            .iter()
+            .map(|d| (((d - near) * scale).clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect()
+    }
+}
+
+/// Decoder for depth and depth-stencil surfaces.
+///
+/// [`DepthStencilDecoder::decode_planes`] separates depth and stencil. Through [`Decoder`], texels
+/// have depth in the first channel and, for formats with stencil, the stencil normalized from
+/// `[0, 255]` in the second channel. Typeless views decode the same as their depth formats, since
+/// they share their memory layout, except that the depth-only `R32FloatX8X24Typeless` and
+/// `R24UnsignedNormalizedX8Typeless` views have no stencil. Texels are written as
+/// [`ChannelType::Float32`] by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DepthStencilDecoder {
+    format: SpecialUncompressedFormat,
+    layout: DepthLayout,
+    /// Whether the stencil is decoded, which depth-only views of depth-stencil formats skip.
+    stencil: bool,
+    channel_type: ChannelType,
+}
+
+impl DepthStencilDecoder {
+    /// Creates a decoder for `format`, failing with [`DecoderError::UnsupportedFormat`] if it
+    /// isn't a depth format or one of its typeless views.
+    pub fn new(format: SpecialUncompressedFormat) -> DecoderResult<DepthStencilDecoder> {
+        use SpecialUncompressedFormat::*;
+        let layout = match format {
+            R32G8X24Typeless
+            | D32FloatS8X24UnsignedInt
+            | R32FloatX8X24Typeless
+            | X32TypelessG8X24UnsignedInt => DepthLayout::D32S8X24,
+            D24UnsignedNormalizedS8UnsignedInt
+            | R24UnsignedNormalizedX8Typeless
+            | X24TypelessG8UnsignedInt => DepthLayout::D24S8,
+            D32Float => DepthLayout::D32,
+            D16UnsignedNormalized => DepthLayout::D16,
+            _ => {
+                return Err(DecoderError::UnsupportedFormat(
+                    TextureFormat::Uncompressed(UncompressedFormat::Other(format)),
+                ))
+            }
+        };
+        let depth_only = matches!(
+            format,
+            R32FloatX8X24Typeless | R24UnsignedNormalizedX8Typeless
+        );
+        Ok(DepthStencilDecoder {
+            format,
+            layout,
+            stencil: layout.has_stencil() && !depth_only,
+            channel_type: ChannelType::Float32,
+        })
+    }
+
+    /// Sets the storage of each decoded channel.
+    pub fn channel_type(mut self, channel_type: ChannelType) -> DepthStencilDecoder {
+        self.channel_type = channel_type;
+        self
+    }
+
+    pub fn format(&self) -> SpecialUncompressedFormat {
+        self.format
+    }
+
+    fn texel_format(&self) -> TexelFormat {
+        let channels = if self.stencil { 2 } else { 1 };
+        TexelFormat::new(channels, self.channel_type, false)
+    }
+
+    fn decode_block(&self, pixel: &[u8], texel: &mut [u8]) {
+        let (depth, stencil) = self.layout.decode_pixel(pixel);
+        let values = [depth, stencil as f32 / 255.0];
+        let size = self.channel_type.size();
+        for (value, out) in values.iter().zip(texel.chunks_exact_mut(size)) {
+            self.channel_type.write(*value, false, out);
+        }
+    }
+
+    /// Decodes a surface into a depth plane and, for formats with stencil, a stencil plane.
+    pub fn decode_planes(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<DepthStencilPlanes> {
+        let block = self.layout.block();
+        check_len(bytes, block.surface_bytes(surface)?)?;
+        let (row_pitch, slice_pitch) = block.pitches(surface)?;
+        let count = surface.width * surface.height * surface.depth;
+
+        let mut depth = Vec::with_capacity(count);
+        let mut stencil = Vec::with_capacity(if self.stencil { count } else { 0 });
+        for z in 0..surface.depth {
+            for y in 0..surface.height {
+                let row = &bytes[z * slice_pitch + y * row_pitch..];
+                for pixel in row.chunks_exact(block.bytes).take(surface.width) {
+                    let (d, s) = self.layout.decode_pixel(pixel);
+                    depth.push(d);
+                    if self.stencil {
+                        stencil.push(s);
+                    }
+                }
+            }
+        }
+        Ok(DepthStencilPlanes {
+            width: surface.width,
+            height: surface.height,
+            slices: surface.depth,
+            depth,
+            stencil: Some(stencil).filter(|_| self.stencil),
+        })
+    }
+}
+
+impl Decoder for DepthStencilDecoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(
+            bytes,
+            surface,
+            self.layout.block(),
+            self.texel_format().size(),
+            buffer,
+            |pixel, texel| self.decode_block(pixel, texel),
+        )
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            self.layout.block(),
+            self.texel_format(),
+            |pixel, texel| self.decode_block(pixel, texel),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, self.layout.block())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use SpecialUncompressedFormat::*;
+
+    fn decode_planes(format: SpecialUncompressedFormat, bytes: &[u8]) -> DepthStencilPlanes {
+        let width = bytes.len()
+            / DepthStencilDecoder::new(format)
+                .unwrap()
+                .layout
+                .block()
+                .bytes;
+        DepthStencilDecoder::new(format)
+            .unwrap()
+            .decode_planes(bytes, SurfaceDescriptor::new(width, 1))
+            .unwrap()
+    }
+
+    fn decode_f32(format: SpecialUncompressedFormat, bytes: &[u8]) -> Vec<f32> {
+        DepthStencilDecoder::new(format)
+            .unwrap()
+            .decode(bytes, SurfaceDescriptor::new(1, 1))
+            .unwrap()
+            .chunks_exact(4)
+            .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn decodes_d24s8() {
+        let bytes = 0xAB80_0000u32.to_le_bytes();
+        let planes = decode_planes(D24UnsignedNormalizedS8UnsignedInt, &bytes);
+        assert!((planes.depth()[0] - 0.5).abs() < 1e-6);
+        assert_eq!(planes.stencil(), Some(&[171][..]));
+
+        let texel = decode_f32(D24UnsignedNormalizedS8UnsignedInt, &bytes);
+        assert_eq!(texel.len(), 2);
+        assert!((texel[0] - 0.5).abs() < 1e-6);
+        assert_eq!(texel[1], 171.0 / 255.0);
+    }
+
+    #[test]
+    fn decodes_d32s8x24() {
+        let mut bytes = 0.25f32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[7, 0xFF, 0xFF, 0xFF]);
+        let planes = decode_planes(D32FloatS8X24UnsignedInt, &bytes);
+        assert_eq!(planes.depth(), [0.25]);
+        assert_eq!(planes.stencil(), Some(&[7][..]));
+        assert_eq!(
+            decode_f32(D32FloatS8X24UnsignedInt, &bytes),
+            [0.25, 7.0 / 255.0]
+        );
+    }
+
+    #[test]
+    fn decodes_depth_only_views_without_stencil() {
+        let bytes = 0xAB80_0000u32.to_le_bytes();
+        let planes = decode_planes(R24UnsignedNormalizedX8Typeless, &bytes);
+        assert!((planes.depth()[0] - 0.5).abs() < 1e-6);
+        assert_eq!(planes.stencil(), None);
+        assert_eq!(decode_f32(R24UnsignedNormalizedX8Typeless, &bytes).len(), 1);
+
+        let mut bytes = 0.25f32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&[7, 0, 0, 0]);
+        let planes = decode_planes(R32FloatX8X24Typeless, &bytes);
+        assert_eq!(planes.depth(), [0.25]);
+        assert_eq!(planes.stencil(), None);
+        assert_eq!(decode_f32(R32FloatX8X24Typeless, &bytes), [0.25]);
+    }
+
+    #[test]
+    fn remaps_depth_to_grayscale() {
+        let bytes: Vec<u8> = [0.0f32, 0.25, 0.5, 1.0, f32::NAN, f32::INFINITY]
+            .iter()
+            .flat_map(|d| d.to_le_bytes())
+            .collect();
+        let planes = decode_planes(D32Float, &bytes);
+        assert_eq!(planes.depth_range(), Some((0.0, 1.0)));
+        assert_eq!(planes.to_grayscale(0.25, 0.75), [0, 0, 128, 255, 0, 255]);
+        assert_eq!(planes.to_grayscale(0.75, 0.25), [255, 255, 128, 0, 0, 0]);
+        assert_eq!(planes.to_grayscale(0.5, 0.5), [0; 6]);
+    }
+}
@@ -0,0 +1,83 @@
+//! Conversions between `f32` and the smaller floating point formats used by textures.
+
+/// Converts the bits of an IEEE 754 half-precision float to `f32`. The conversion is exact.
+pub(crate) fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half as u32) & 0x8000) << 16;
+    let exponent = ((half >> 10) & 0x1F) as u32;
+    let mantissa = (half & 0x3FF) as u32;
+    match exponent {
+        0 => {
+            // Zero and denormals, which are all normal numbers in f32
+            let value = mantissa as f32 / (1 << 24) as f32;
+            f32::from_bits(sign | value.to_bits())
+        }
+        0x1F => f32::from_bits(sign | 0x7F80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
+
+/// Converts the bits of an unsigned float with a 5-bit exponent and a mantissa of `mantissa_bits`,
+/// as used by the 10 and 11-bit channels of `R11G11B10_FLOAT`, to `f32`. The conversion is exact.
+pub(crate) fn unsigned_float_to_f32(bits: u32, mantissa_bits: u32) -> f32 {
+    // These share the exponent of half floats, so widen the mantissa and reuse that conversion
+    let bits = bits & ((1 << (mantissa_bits + 5)) - 1);
+    f16_to_f32((bits << (10 - mantissa_bits)) as u16)
+}
+
+/// Converts the three 9-bit mantissas and shared 5-bit exponent of `R9G9B9E5_SHAREDEXP` to `f32`.
+/// The conversion is exact.
+pub(crate) fn rgb9e5_to_f32(packed: u32) -> [f32; 3] {
+    // Mantissas have no implicit leading one, so values are `mantissa * 2^(exponent - 15 - 9)`
+    let scale = f32::from_bits(((packed >> 27) + 127 - 24) << 23);
+    [
+        (packed & 0x1FF) as f32 * scale,
+        ((packed >> 9) & 0x1FF) as f32 * scale,
+        ((packed >> 18) & 0x1FF) as f32 * scale,
+    ]
+}
+
+/// Converts an `f32` to the bits of an IEEE 754 half-precision float, rounding to nearest even.
+///
+/// Values too large for a half float become infinity and NaNs stay NaN.
+pub(crate) fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+
+    if exponent == 0xFF {
+        let nan = if mantissa != 0 {
+            0x200 | (mantissa >> 13) as u16
+        } else {
+            0
+        };
+        return sign | 0x7C00 | nan;
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        return sign | round_shift(mantissa, shift) as u16;
+    }
+    // A carry out of the mantissa correctly bumps the exponent, up to infinity
+    sign | round_shift(((exponent as u32) << 23) | mantissa, 13) as u16
+}
+
+/// Shifts `value` right by `shift` bits, rounding to nearest even.
+pub(crate) fn round_shift(value: u32, shift: u32) -> u32 {
+    let truncated = value >> shift;
+    let remainder = value & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    if remainder > halfway || (remainder == halfway && truncated & 1 == 1) {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
@@ -1,26 +1,1154 @@
+use crate::float::{f16_to_f32, f32_to_f16};
+use ddsfmt::TextureFormat;
 use thiserror::Error;
 
 mod bc1;
+mod bc2;
+mod bc3;
+mod bc4;
+mod bc5;
+mod bc6h;
+mod bc7;
+mod blocks;
+mod bptc;
+mod depth;
+mod float;
+mod packed;
+mod packed_float;
+mod palette;
+mod planar;
+mod sampler;
+mod uncompressed;
+mod yuv;
+
+pub use bc1::{Bc1Decoder, Bc1Encoder};
+pub use bc2::{Bc2Decoder, Bc2Encoder};
+pub use bc3::{Bc3Decoder, Bc3Encoder};
+pub use bc4::{Bc4Decoder, Bc4Encoder};
+pub use bc5::{Bc5Decoder, Bc5Encoder};
+pub use bc6h::Bc6hDecoder;
+pub use bc7::{Bc7Decoder, Bc7Encoder, Bc7Profile};
+pub use blocks::{Block, Blocks};
+pub use depth::{DepthStencilDecoder, DepthStencilPlanes};
+pub use packed::{Packed422Decoder, Packed422Format, PackedYuvDecoder, PackedYuvFormat};
+pub use packed_float::{PackedFloatDecoder, PackedFloatFormat};
+pub use palette::{Palette, PaletteDecoder, PaletteFormat};
+pub use planar::{PlanarYuvDecoder, PlanarYuvFormat};
+pub use sampler::{AddressMode, Filter, Sampler};
+pub use uncompressed::UncompressedDecoder;
+pub use yuv::{ColorMatrix, ColorRange};
 
 #[derive(Error, Debug)]
-pub enum DecoderError {}
+pub enum DecoderError {
+    #[error("input data is truncated: expected {expected} bytes, found {actual}")]
+    TruncatedInput { expected: usize, actual: usize },
+    #[error("pixel coordinates ({0}, {1}) are outside of the surface")]
+    OutOfBounds(usize, usize),
+    #[error("pitch of {actual} bytes is smaller than the {expected} bytes it must span")]
+    SizeMismatch { expected: usize, actual: usize },
+    #[error("texture format {0:?} is not supported")]
+    UnsupportedFormat(TextureFormat),
+}
 
 pub type DecoderResult<T> = std::result::Result<T, DecoderError>;
 
-pub struct DecodedBlock {}
+#[derive(Error, Debug)]
+pub enum EncoderError {
+    #[error("input data is truncated: expected {expected} bytes, found {actual}")]
+    TruncatedInput { expected: usize, actual: usize },
+    #[error(
+        "pitch of {actual} bytes must be a whole number of channels of at least {expected} bytes"
+    )]
+    SizeMismatch { expected: usize, actual: usize },
+    #[error("texture format {0:?} is not supported")]
+    UnsupportedFormat(TextureFormat),
+    #[error("{0} input is not supported by this encoder")]
+    UnsupportedInput(&'static str),
+}
+
+pub type EncoderResult<T> = std::result::Result<T, EncoderError>;
+
+/// Storage used for each channel of decoded texels.
+///
+/// Multi-byte values are written in native byte order. Normalized types clamp values outside of
+/// their range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChannelType {
+    /// 8-bit normalized integer. Signed formats produce two's complement SNORM values.
+    Norm8,
+    /// 16-bit normalized integer. Signed formats produce two's complement SNORM values.
+    Norm16,
+    /// 16-bit IEEE 754 half-precision float.
+    Float16,
+    /// 32-bit float in the range `[0, 1]`, or `[-1, 1]` for signed formats. Integer formats keep
+    /// their integer values.
+    Float32,
+}
+
+impl ChannelType {
+    /// Size of a single channel in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            ChannelType::Norm8 => 1,
+            ChannelType::Norm16 | ChannelType::Float16 => 2,
+            ChannelType::Float32 => 4,
+        }
+    }
+
+    /// Writes a normalized value to `out`, which must be exactly `self.size()` bytes. Normalized
+    /// channel types saturate values outside of their range.
+    pub(crate) fn write(self, value: f32, signed: bool, out: &mut [u8]) {
+        let clamped = if signed {
+            value.clamp(-1.0, 1.0)
+        } else {
+            value.clamp(0.0, 1.0)
+        };
+        match (self, signed) {
+            (ChannelType::Norm8, false) => out[0] = (clamped * 255.0).round() as u8,
+            (ChannelType::Norm8, true) => out[0] = (clamped * 127.0).round() as i8 as u8,
+            (ChannelType::Norm16, false) => {
+                out.copy_from_slice(&((clamped * 65535.0).round() as u16).to_ne_bytes())
+            }
+            (ChannelType::Norm16, true) => {
+                out.copy_from_slice(&((clamped * 32767.0).round() as i16).to_ne_bytes())
+            }
+            (ChannelType::Float16, _) => out.copy_from_slice(&f32_to_f16(value).to_ne_bytes()),
+            (ChannelType::Float32, _) => out.copy_from_slice(&value.to_ne_bytes()),
+        }
+    }
+
+    /// Reads back a value written by [`ChannelType::write`] from exactly `self.size()` bytes.
+    pub(crate) fn read(self, bytes: &[u8], signed: bool) -> f32 {
+        match (self, signed) {
+            (ChannelType::Norm8, false) => bytes[0] as f32 / 255.0,
+            (ChannelType::Norm8, true) => (bytes[0] as i8 as f32 / 127.0).max(-1.0),
+            (ChannelType::Norm16, false) => {
+                u16::from_ne_bytes([bytes[0], bytes[1]]) as f32 / 65535.0
+            }
+            (ChannelType::Norm16, true) => {
+                (i16::from_ne_bytes([bytes[0], bytes[1]]) as f32 / 32767.0).max(-1.0)
+            }
+            (ChannelType::Float16, _) => f16_to_f32(u16::from_ne_bytes([bytes[0], bytes[1]])),
+            (ChannelType::Float32, _) => {
+                f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+            }
+        }
+    }
+}
+
+/// Channels written for each texel by a decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct TexelFormat {
+    /// Number of channels, starting from red.
+    pub channels: usize,
+    pub channel_type: ChannelType,
+    pub signed: bool,
+}
+
+impl TexelFormat {
+    pub const RGBA8: TexelFormat = TexelFormat::new(4, ChannelType::Norm8, false);
+
+    pub const fn new(channels: usize, channel_type: ChannelType, signed: bool) -> TexelFormat {
+        TexelFormat {
+            channels,
+            channel_type,
+            signed,
+        }
+    }
+
+    /// Size of a texel in bytes.
+    pub fn size(self) -> usize {
+        self.channels * self.channel_type.size()
+    }
+
+    /// Reads a texel as RGBA, with missing color channels as 0 and missing alpha as opaque.
+    fn read_rgba(self, texel: &[u8]) -> [f32; 4] {
+        let mut rgba = [0.0, 0.0, 0.0, 1.0];
+        let size = self.channel_type.size();
+        for (value, bytes) in rgba.iter_mut().zip(texel.chunks_exact(size)) {
+            *value = self.channel_type.read(bytes, self.signed);
+        }
+        rgba
+    }
+}
+
+/// Largest number of texels in a block of any supported format.
+const MAX_BLOCK_TEXELS: usize = 16;
+
+/// The decoded texels of a single block, as returned by [`Decoder::decode_at_pixel`].
+///
+/// Texels are stored as RGBA with the same values and precision the decoder writes through
+/// [`Decoder::decode`], so a block decodes the same whether it is read on its own or as part of a
+/// surface. Missing color channels are 0 and missing alpha is opaque.
+///
+/// Blocks at the right and bottom edges of a surface may extend past it. Only the first
+/// [`valid_size`](DecodedBlock::valid_size) texels of each row and column are part of the image,
+/// and the values of the remaining texels are unspecified.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodedBlock {
+    origin: (usize, usize),
+    size: (usize, usize),
+    valid_size: (usize, usize),
+    texels: [[f32; 4]; MAX_BLOCK_TEXELS],
+}
+
+impl DecodedBlock {
+    /// Creates an empty block of `size` texels covering the pixel at `coords`.
+    fn empty(surface: SurfaceDescriptor, size: (usize, usize), coords: (usize, usize)) -> Self {
+        let origin = (coords.0 / size.0 * size.0, coords.1 / size.1 * size.1);
+        DecodedBlock {
+            origin,
+            size,
+            valid_size: (
+                (surface.width - origin.0).min(size.0),
+                (surface.height - origin.1).min(size.1),
+            ),
+            texels: [[0.0; 4]; MAX_BLOCK_TEXELS],
+        }
+    }
+
+    /// Position of the top left texel of the block in the surface.
+    pub fn origin(&self) -> (usize, usize) {
+        self.origin
+    }
+
+    /// Size of the block in texels, as `(width, height)`.
+    pub fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    /// Number of texels in each row and column of the block that lie inside the surface.
+    pub fn valid_size(&self) -> (usize, usize) {
+        self.valid_size
+    }
+
+    /// Returns the texel at `(x, y)` within the block as RGBA8.
+    ///
+    /// Values are clamped to `[0, 1]` before being scaled, so HDR and signed texels should be read
+    /// with [`texel_f32`](DecodedBlock::texel_f32) instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is outside of the block.
+    pub fn texel(&self, x: usize, y: usize) -> [u8; 4] {
+        let texel = self.texel_f32(x, y);
+        let mut rgba = [0; 4];
+        for (out, value) in rgba.iter_mut().zip(texel.iter()) {
+            *out = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        rgba
+    }
+
+    /// Returns the texel at `(x, y)` within the block as RGBA floats.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(x, y)` is outside of the block.
+    pub fn texel_f32(&self, x: usize, y: usize) -> [f32; 4] {
+        assert!(
+            x < self.size.0 && y < self.size.1,
+            "texel ({}, {}) is outside of the block",
+            x,
+            y
+        );
+        self.texels[y * self.size.0 + x]
+    }
 
-pub struct Blocks {}
+    /// Returns the texel of the surface at pixel `(x, y)`, if it is inside the block.
+    pub fn pixel_f32(&self, x: usize, y: usize) -> Option<[f32; 4]> {
+        let (bx, by) = (x.checked_sub(self.origin.0)?, y.checked_sub(self.origin.1)?);
+        if bx < self.valid_size.0 && by < self.valid_size.1 {
+            Some(self.texels[by * self.size.0 + bx])
+        } else {
+            None
+        }
+    }
 
+    /// All texels of the block in row-major order.
+    pub fn texels(&self) -> &[[f32; 4]] {
+        &self.texels[..self.size.0 * self.size.1]
+    }
+}
+
+/// Dimensions and memory layout of an encoded surface.
+///
+/// Rows are rows of blocks for block compressed formats and rows of pixels otherwise. Pitches are
+/// in bytes, and `None` means rows or slices directly follow each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SurfaceDescriptor {
+    pub width: usize,
+    pub height: usize,
+    /// Number of depth slices, 1 for anything but volume textures.
+    pub depth: usize,
+    /// Distance from the start of a row to the start of the next.
+    pub row_pitch: Option<usize>,
+    /// Distance from the start of a depth slice to the start of the next.
+    pub slice_pitch: Option<usize>,
+}
+
+impl SurfaceDescriptor {
+    /// Describes a tightly packed 2D surface.
+    pub fn new(width: usize, height: usize) -> SurfaceDescriptor {
+        SurfaceDescriptor {
+            width,
+            height,
+            depth: 1,
+            row_pitch: None,
+            slice_pitch: None,
+        }
+    }
+}
+
+/// Decodes surfaces of a texture format into texels.
+///
+/// The trait is object safe, so decoders for any format can be used through `dyn Decoder`, as
+/// returned by [`decoder_for`].
 pub trait Decoder {
-    fn decode<T: AsRef<[u8]>>(&self, bytes: T) -> DecoderResult<Vec<u8>>;
+    /// Decodes a surface into a new buffer of tightly packed, row-major texels.
+    fn decode(&self, bytes: &[u8], surface: SurfaceDescriptor) -> DecoderResult<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.decode_to(bytes, surface, &mut buffer)?;
+        Ok(buffer)
+    }
 
-    fn decode_to<T: AsRef<[u8]>>(&self, bytes: T, buffer: &mut Vec<u8>) -> DecoderResult<()>;
+    /// Decodes a surface into `buffer`, replacing its contents.
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()>;
 
-    fn decode_at_pixel<T: AsRef<[u8]>>(
+    /// Decodes the block containing the pixel at `coords` in the first slice of a surface.
+    ///
+    /// Uncompressed formats decode a single pixel, or the group of pixels stored together for
+    /// subsampled and sub-byte formats.
+    fn decode_at_pixel(
         &self,
-        bytes: T,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
         coords: (usize, usize),
     ) -> DecoderResult<DecodedBlock>;
 
-    fn blocks<T: AsRef<[u8]>>(&self, bytes: T) -> Blocks;
+    /// Returns an iterator over the encoded blocks of a surface.
+    ///
+    /// Fails with [`DecoderError::UnsupportedFormat`] for planar formats, whose blocks aren't
+    /// stored contiguously.
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>>;
+}
+
+/// Texels to encode, tightly packed in row-major order unless the surface has a row pitch.
+///
+/// Encoders read every input as RGBA, with missing color channels set to 0 and missing alpha to
+/// 1. Pitches are in bytes like any other and must be multiples of the channel size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncoderInput<'a> {
+    /// 8-bit normalized channels.
+    Rgba8(&'a [u8]),
+    /// Float channels, in `[0, 1]` for normalized formats or `[-1, 1]` for signed formats.
+    Rgba32F(&'a [f32]),
+    /// A single 16-bit normalized channel.
+    R16(&'a [u16]),
+    /// Two 16-bit normalized channels.
+    Rg16(&'a [u16]),
+    /// A single 16-bit signed normalized channel, with both -32767 and -32768 mapping to -1.0.
+    R16Snorm(&'a [i16]),
+    /// Two 16-bit signed normalized channels.
+    Rg16Snorm(&'a [i16]),
+    /// A single float channel, with the same range as [`Rgba32F`](EncoderInput::Rgba32F).
+    R32F(&'a [f32]),
+    /// Two float channels.
+    Rg32F(&'a [f32]),
+}
+
+impl<'a> EncoderInput<'a> {
+    /// Name of the input type, as reported by [`EncoderError::UnsupportedInput`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            EncoderInput::Rgba8(_) => "RGBA8",
+            EncoderInput::Rgba32F(_) => "RGBA32F",
+            EncoderInput::R16(_) => "R16",
+            EncoderInput::Rg16(_) => "RG16",
+            EncoderInput::R16Snorm(_) => "R16_SNORM",
+            EncoderInput::Rg16Snorm(_) => "RG16_SNORM",
+            EncoderInput::R32F(_) => "R32F",
+            EncoderInput::Rg32F(_) => "RG32F",
+        }
+    }
+
+    /// Returns the number of channels, the size of each channel in bytes and the length of the
+    /// input in bytes.
+    fn layout(&self) -> (usize, usize, usize) {
+        match self {
+            EncoderInput::Rgba8(bytes) => (4, 1, bytes.len()),
+            EncoderInput::Rgba32F(values) => (4, 4, values.len() * 4),
+            EncoderInput::R16(values) => (1, 2, values.len() * 2),
+            EncoderInput::Rg16(values) => (2, 2, values.len() * 2),
+            EncoderInput::R16Snorm(values) => (1, 2, values.len() * 2),
+            EncoderInput::Rg16Snorm(values) => (2, 2, values.len() * 2),
+            EncoderInput::R32F(values) => (1, 4, values.len() * 4),
+            EncoderInput::Rg32F(values) => (2, 4, values.len() * 4),
+        }
+    }
+
+    /// Reads the texel starting at byte `offset` as RGBA.
+    fn read_rgba(&self, offset: usize) -> [f32; 4] {
+        let (channels, size, _) = self.layout();
+        let index = offset / size;
+        let mut texel = [0.0, 0.0, 0.0, 1.0];
+        for (c, out) in texel.iter_mut().take(channels).enumerate() {
+            *out = match self {
+                EncoderInput::Rgba8(bytes) => bytes[index + c] as f32 / 255.0,
+                EncoderInput::R16(values) | EncoderInput::Rg16(values) => {
+                    values[index + c] as f32 / 65535.0
+                }
+                EncoderInput::R16Snorm(values) | EncoderInput::Rg16Snorm(values) => {
+                    (values[index + c] as f32 / 32767.0).max(-1.0)
+                }
+                EncoderInput::Rgba32F(values)
+                | EncoderInput::R32F(values)
+                | EncoderInput::Rg32F(values) => values[index + c],
+            };
+        }
+        texel
+    }
+}
+
+/// Trade-off between encoding speed and quality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Quality {
+    /// Fastest encoding, for previews and iteration.
+    Fast,
+    #[default]
+    Normal,
+    /// Most exhaustive search, for final assets.
+    Slow,
+}
+
+/// Options of a single [`Encoder`] call.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncoderOptions {
+    pub quality: Quality,
+    /// Relative importance of the RGBA channels when measuring the error of an encoding. Defaults
+    /// to equal weights.
+    pub channel_weights: [f32; 4],
+}
+
+impl EncoderOptions {
+    pub fn new(quality: Quality) -> EncoderOptions {
+        EncoderOptions {
+            quality,
+            channel_weights: [1.0; 4],
+        }
+    }
+}
+
+impl Default for EncoderOptions {
+    fn default() -> EncoderOptions {
+        EncoderOptions::new(Quality::default())
+    }
+}
+
+/// Encodes texels into surfaces of a texture format.
+///
+/// Input rows are `surface.width` texels wide unless `surface.row_pitch` gives their distance in
+/// bytes, and slices follow each other in the same way. Output blocks are tightly packed, with
+/// edge blocks padded by repeating the last column and row of texels. Like [`Decoder`], the trait
+/// is object safe.
+pub trait Encoder {
+    /// Encodes a surface into a new buffer of blocks.
+    fn encode(
+        &self,
+        input: EncoderInput<'_>,
+        surface: SurfaceDescriptor,
+        options: EncoderOptions,
+    ) -> EncoderResult<Vec<u8>> {
+        let mut buffer = Vec::new();
+        self.encode_to(input, surface, options, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Encodes a surface into `buffer`, replacing its contents.
+    fn encode_to(
+        &self,
+        input: EncoderInput<'_>,
+        surface: SurfaceDescriptor,
+        options: EncoderOptions,
+        buffer: &mut Vec<u8>,
+    ) -> EncoderResult<()>;
+}
+
+/// Returns a decoder for surfaces of `format` with the default options of that decoder.
+///
+/// Returns `None` for unknown and opaque formats, and for palettized formats, which need a
+/// [`Palette`] supplied to [`PaletteDecoder`]. The decoder can be shared between threads.
+pub fn decoder_for(format: TextureFormat) -> Option<Box<dyn Decoder + Send + Sync>> {
+    use ddsfmt::{CompressedFormat::*, SpecialUncompressedFormat::*, UncompressedFormat::Other};
+    let decoder: Box<dyn Decoder + Send + Sync> = match format {
+        TextureFormat::Compressed(format) => match format {
+            BC1(t) => Box::new(Bc1Decoder::new(t)),
+            BC2(t) => Box::new(Bc2Decoder::new(t)),
+            BC3(t) => Box::new(Bc3Decoder::new(t)),
+            BC4(t) => Box::new(Bc4Decoder::new(t)),
+            BC5(t) => Box::new(Bc5Decoder::new(t)),
+            BC6H(t) => Box::new(Bc6hDecoder::new(t)),
+            BC7(t) => Box::new(Bc7Decoder::new(t)),
+            R8G8B8G8 => Box::new(Packed422Decoder::new(Packed422Format::R8G8B8G8)),
+            G8R8G8B8 => Box::new(Packed422Decoder::new(Packed422Format::G8R8G8B8)),
+            YUY2 => Box::new(Packed422Decoder::new(Packed422Format::YUY2)),
+            UYVY => Box::new(Packed422Decoder::new(Packed422Format::UYVY)),
+            AYUV => Box::new(PackedYuvDecoder::new(PackedYuvFormat::AYUV)),
+            Y410 => Box::new(PackedYuvDecoder::new(PackedYuvFormat::Y410)),
+            Y416 => Box::new(PackedYuvDecoder::new(PackedYuvFormat::Y416)),
+            Y210 => Box::new(PackedYuvDecoder::new(PackedYuvFormat::Y210)),
+            Y216 => Box::new(PackedYuvDecoder::new(PackedYuvFormat::Y216)),
+            NV12 => Box::new(PlanarYuvDecoder::new(PlanarYuvFormat::NV12)),
+            NV11 => Box::new(PlanarYuvDecoder::new(PlanarYuvFormat::NV11)),
+            P010 => Box::new(PlanarYuvDecoder::new(PlanarYuvFormat::P010)),
+            P016 => Box::new(PlanarYuvDecoder::new(PlanarYuvFormat::P016)),
+            Opaque420 | AI44 | IA44 | P208 | V208 | V408 => return None,
+        },
+        TextureFormat::Uncompressed(Other(format)) => match format {
+            R9G9B9E5SharedExponent => Box::new(PackedFloatDecoder::new(
+                PackedFloatFormat::R9G9B9E5SharedExp,
+            )),
+            R10G10B10FixedPointBiasA2UnsignedNormalized => Box::new(PackedFloatDecoder::new(
+                PackedFloatFormat::R10G10B10XrBiasA2,
+            )),
+            P8 | A8P8 => return None,
+            _ => Box::new(DepthStencilDecoder::new(format).ok()?),
+        },
+        TextureFormat::Uncompressed(format) => Box::new(UncompressedDecoder::new(format).ok()?),
+        TextureFormat::Unknown(_) => return None,
+    };
+    Some(decoder)
+}
+
+/// Dimensions and encoded size of the blocks a surface is divided into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct BlockLayout {
+    /// Block width in texels.
+    pub width: usize,
+    /// Block height in texels.
+    pub height: usize,
+    /// Encoded size of a single block in bytes.
+    pub bytes: usize,
+}
+
+impl BlockLayout {
+    pub const fn new(width: usize, height: usize, bytes: usize) -> BlockLayout {
+        BlockLayout {
+            width,
+            height,
+            bytes,
+        }
+    }
+
+    /// Number of blocks needed to cover a surface, as `(columns, rows)`.
+    pub fn block_count(&self, width: usize, height: usize) -> (usize, usize) {
+        (width.div_ceil(self.width), height.div_ceil(self.height))
+    }
+
+    /// Row and slice pitches of a surface, defaulting to tightly packed blocks.
+    pub fn pitches(&self, surface: SurfaceDescriptor) -> DecoderResult<(usize, usize)> {
+        let (columns, rows) = self.block_count(surface.width, surface.height);
+        let row_pitch = check_pitch(surface.row_pitch, columns * self.bytes)?;
+        let slice_pitch = check_pitch(surface.slice_pitch, row_pitch * rows)?;
+        Ok((row_pitch, slice_pitch))
+    }
+
+    /// Number of encoded bytes needed to cover a surface.
+    pub fn surface_bytes(&self, surface: SurfaceDescriptor) -> DecoderResult<usize> {
+        let (row_pitch, slice_pitch) = self.pitches(surface)?;
+        let (columns, rows) = self.block_count(surface.width, surface.height);
+        Ok(required_len(
+            surface.depth,
+            slice_pitch,
+            rows,
+            row_pitch,
+            columns * self.bytes,
+        ))
+    }
+
+    /// Returns the encoded bytes of the block containing the given pixel of the first slice.
+    pub fn block_at<'a>(
+        &self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+        (x, y): (usize, usize),
+    ) -> DecoderResult<&'a [u8]> {
+        if x >= surface.width || y >= surface.height || surface.depth == 0 {
+            return Err(DecoderError::OutOfBounds(x, y));
+        }
+        check_len(bytes, self.surface_bytes(surface)?)?;
+        let (row_pitch, _) = self.pitches(surface)?;
+        let offset = (y / self.height) * row_pitch + (x / self.width) * self.bytes;
+        Ok(&bytes[offset..offset + self.bytes])
+    }
+}
+
+pub(crate) fn check_len(bytes: &[u8], expected: usize) -> DecoderResult<()> {
+    if bytes.len() < expected {
+        Err(DecoderError::TruncatedInput {
+            expected,
+            actual: bytes.len(),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns `pitch`, or `packed` if it is `None`, failing if `pitch` can't hold `packed` bytes.
+pub(crate) fn check_pitch(pitch: Option<usize>, packed: usize) -> DecoderResult<usize> {
+    match pitch {
+        Some(pitch) if pitch < packed => Err(DecoderError::SizeMismatch {
+            expected: packed,
+            actual: pitch,
+        }),
+        Some(pitch) => Ok(pitch),
+        None => Ok(packed),
+    }
+}
+
+/// Number of bytes spanned by `slices` slices of `rows` rows of `row_size` bytes each.
+///
+/// Only the data of the last row is required, not the padding up to the next pitch.
+pub(crate) fn required_len(
+    slices: usize,
+    slice_pitch: usize,
+    rows: usize,
+    row_pitch: usize,
+    row_size: usize,
+) -> usize {
+    if slices == 0 || rows == 0 || row_size == 0 {
+        0
+    } else {
+        (slices - 1) * slice_pitch + (rows - 1) * row_pitch + row_size
+    }
+}
+
+/// Decodes a surface of fixed-size blocks into a tightly packed, row-major buffer.
+///
+/// `decode_block` is called once per block with the encoded block bytes and a scratch buffer of
+/// `layout.width * layout.height * texel_size` bytes to fill with row-major texels. Texels that
+/// fall outside the surface at the right and bottom edges are discarded. Depth slices are
+/// written one after another.
+pub(crate) fn decode_block_surface<F>(
+    bytes: &[u8],
+    surface: SurfaceDescriptor,
+    layout: BlockLayout,
+    texel_size: usize,
+    buffer: &mut Vec<u8>,
+    mut decode_block: F,
+) -> DecoderResult<()>
+where
+    F: FnMut(&[u8], &mut [u8]),
+{
+    check_len(bytes, layout.surface_bytes(surface)?)?;
+    let (row_pitch, slice_pitch) = layout.pitches(surface)?;
+    let (width, height) = (surface.width, surface.height);
+    let (columns, rows) = layout.block_count(width, height);
+    let row_size = width * texel_size;
+    let slice_size = row_size * height;
+    let block_row_size = layout.width * texel_size;
+    let mut texels = vec![0; block_row_size * layout.height];
+
+    buffer.clear();
+    buffer.resize(slice_size * surface.depth, 0);
+    for (z, output) in buffer.chunks_exact_mut(slice_size.max(1)).enumerate() {
+        for block_y in 0..rows {
+            let offset = z * slice_pitch + block_y * row_pitch;
+            let row = &bytes[offset..offset + columns * layout.bytes];
+            for (block_x, block) in row.chunks_exact(layout.bytes).enumerate() {
+                decode_block(block, &mut texels);
+
+                let (bx, by) = (block_x * layout.width, block_y * layout.height);
+                let copy_size = (width - bx).min(layout.width) * texel_size;
+                for (ty, src) in texels
+                    .chunks_exact(block_row_size)
+                    .take(height - by)
+                    .enumerate()
+                {
+                    let offset = (by + ty) * row_size + bx * texel_size;
+                    output[offset..offset + copy_size].copy_from_slice(&src[..copy_size]);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decodes the block containing pixel `coords` of the first slice of a surface.
+///
+/// `decode_block` is called with the encoded block bytes and a buffer to fill with row-major
+/// texels of `format`, as for [`decode_block_surface`].
+pub(crate) fn decode_block_at<F>(
+    bytes: &[u8],
+    surface: SurfaceDescriptor,
+    coords: (usize, usize),
+    layout: BlockLayout,
+    format: TexelFormat,
+    decode_block: F,
+) -> DecoderResult<DecodedBlock>
+where
+    F: FnOnce(&[u8], &mut [u8]),
+{
+    let block = layout.block_at(bytes, surface, coords)?;
+    let mut texels = vec![0; layout.width * layout.height * format.size()];
+    decode_block(block, &mut texels);
+
+    let mut decoded = DecodedBlock::empty(surface, (layout.width, layout.height), coords);
+    for (out, texel) in decoded
+        .texels
+        .iter_mut()
+        .zip(texels.chunks_exact(format.size()))
+    {
+        *out = format.read_rgba(texel);
+    }
+    Ok(decoded)
+}
+
+/// Encodes a surface into tightly packed blocks of `layout`.
+///
+/// `encode_block` is called once per block with its row-major RGBA texels and the encoded block
+/// to fill. Blocks extending past the right and bottom edges of the surface repeat its last
+/// column and row. With the `rayon` feature, blocks are encoded in parallel.
+pub(crate) fn encode_block_surface<F>(
+    input: EncoderInput<'_>,
+    surface: SurfaceDescriptor,
+    layout: BlockLayout,
+    buffer: &mut Vec<u8>,
+    encode_block: F,
+) -> EncoderResult<()>
+where
+    F: Fn(&[[f32; 4]], &mut [u8]) + Sync,
+{
+    let (channels, channel_size, len) = input.layout();
+    let texel_size = channels * channel_size;
+    let pitch = |pitch: Option<usize>, packed| -> EncoderResult<usize> {
+        let mismatch = |expected| EncoderError::SizeMismatch {
+            expected,
+            actual: pitch.unwrap_or_default(),
+        };
+        let pitch = check_pitch(pitch, packed).map_err(|_| mismatch(packed))?;
+        // Channels are read whole, so rows and slices must start on a channel boundary
+        match pitch % channel_size {
+            0 => Ok(pitch),
+            rest => Err(mismatch(pitch + channel_size - rest)),
+        }
+    };
+    let (width, height) = (surface.width, surface.height);
+    let row_pitch = pitch(surface.row_pitch, width * texel_size)?;
+    let slice_pitch = pitch(surface.slice_pitch, row_pitch * height)?;
+    let expected = required_len(
+        surface.depth,
+        slice_pitch,
+        height,
+        row_pitch,
+        width * texel_size,
+    );
+    if len < expected {
+        return Err(EncoderError::TruncatedInput {
+            expected,
+            actual: len,
+        });
+    }
+
+    let (columns, rows) = layout.block_count(width, height);
+    let encode = |index: usize, block: &mut [u8]| {
+        let (block_x, block_y) = (index % columns, (index / columns) % rows);
+        let z = index / (columns * rows);
+        let mut texels = [[0.0; 4]; MAX_BLOCK_TEXELS];
+        let texels = &mut texels[..layout.width * layout.height];
+        for (i, texel) in texels.iter_mut().enumerate() {
+            let x = (block_x * layout.width + i % layout.width).min(width - 1);
+            let y = (block_y * layout.height + i / layout.width).min(height - 1);
+            *texel = input.read_rgba(z * slice_pitch + y * row_pitch + x * texel_size);
+        }
+        encode_block(texels, block);
+    };
+
+    buffer.clear();
+    buffer.resize(columns * rows * surface.depth * layout.bytes, 0);
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        buffer
+            .par_chunks_exact_mut(layout.bytes)
+            .enumerate()
+            .for_each(|(index, block)| encode(index, block));
+    }
+    #[cfg(not(feature = "rayon"))]
+    for (index, block) in buffer.chunks_exact_mut(layout.bytes).enumerate() {
+        encode(index, block);
+    }
+    Ok(())
+}
+
+/// Converts premultiplied RGBA8 texels back to straight alpha in place.
+///
+/// Fully transparent texels carry no color information and are left as is.
+pub(crate) fn unpremultiply_rgba8(texels: &mut [u8]) {
+    for texel in texels.chunks_exact_mut(4) {
+        let alpha = texel[3] as u32;
+        if alpha != 0 && alpha != 255 {
+            for channel in &mut texel[..3] {
+                *channel = ((*channel as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decoder_for_is_shareable_between_threads() {
+        let decoder = decoder_for(ddsfmt::formats::R8G8B8A8_UNORM).unwrap();
+        let pixel = [1, 2, 3, 4];
+        let surface = SurfaceDescriptor::new(1, 1);
+        let decoded = std::thread::scope(|scope| {
+            scope
+                .spawn(|| decoder.decode(&pixel, surface).unwrap())
+                .join()
+                .unwrap()
+        });
+        assert_eq!(decoded, decoder.decode(&pixel, surface).unwrap());
+    }
+
+    #[test]
+    fn encoder_pitches_must_align_to_channels() {
+        let texels = [0u16; 40];
+        let encoder = Bc4Encoder::new(ddsfmt::SignedCompressionType::UnsignedNormalized);
+        let encode = |row_pitch, slice_pitch| {
+            let surface = SurfaceDescriptor {
+                depth: 2,
+                row_pitch: Some(row_pitch),
+                slice_pitch,
+                ..SurfaceDescriptor::new(4, 4)
+            };
+            encoder.encode(
+                EncoderInput::R16(&texels),
+                surface,
+                EncoderOptions::default(),
+            )
+        };
+        assert!(encode(10, None).is_ok());
+        assert!(matches!(
+            encode(9, None),
+            Err(EncoderError::SizeMismatch {
+                expected: 10,
+                actual: 9
+            })
+        ));
+        assert!(matches!(
+            encode(6, None),
+            Err(EncoderError::SizeMismatch {
+                expected: 8,
+                actual: 6
+            })
+        ));
+        assert!(matches!(
+            encode(8, Some(33)),
+            Err(EncoderError::SizeMismatch {
+                expected: 34,
+                actual: 33
+            })
+        ));
+    }
+
+    /// A BC1 block of a single RGB565 color.
+    fn solid_bc1(color: u16) -> [u8; 8] {
+        let [lo, hi] = color.to_le_bytes();
+        [lo, hi, lo, hi, 0, 0, 0, 0]
+    }
+
+    fn r8_volume(row_pitch: Option<usize>, slice_pitch: Option<usize>) -> SurfaceDescriptor {
+        SurfaceDescriptor {
+            depth: 2,
+            row_pitch,
+            slice_pitch,
+            ..SurfaceDescriptor::new(2, 2)
+        }
+    }
+
+    #[test]
+    fn decodes_padded_block_rows() {
+        // 2x2 blocks with 8 bytes of padding after the first row, but not after the last
+        let mut bytes = Vec::new();
+        for (i, color) in [0xF800, 0x07E0, 0x001F, 0xFFFF].iter().enumerate() {
+            bytes.extend_from_slice(&solid_bc1(*color));
+            if i == 1 {
+                bytes.extend_from_slice(&[0xEE; 8]);
+            }
+        }
+        let surface = SurfaceDescriptor {
+            row_pitch: Some(24),
+            ..SurfaceDescriptor::new(5, 5)
+        };
+        let texels = decoder_for(ddsfmt::formats::BC1_UNORM)
+            .unwrap()
+            .decode(&bytes, surface)
+            .unwrap();
+        assert_eq!(texels.len(), 5 * 5 * 4);
+        for (i, texel) in texels.chunks_exact(4).enumerate() {
+            let expected = match (i % 5 == 4, i / 5 == 4) {
+                (false, false) => [255, 0, 0, 255],
+                (true, false) => [0, 255, 0, 255],
+                (false, true) => [0, 0, 255, 255],
+                (true, true) => [255, 255, 255, 255],
+            };
+            assert_eq!(texel, expected, "pixel {}", i);
+        }
+    }
+
+    #[test]
+    fn decodes_volume_slices() {
+        // Rows padded to 3 bytes and slices to 8
+        let bytes = [1, 2, 0, 3, 4, 0, 0, 0, 5, 6, 0, 7, 8];
+        let texels = decoder_for(ddsfmt::formats::R8_UNORM)
+            .unwrap()
+            .decode(&bytes, r8_volume(Some(3), Some(8)))
+            .unwrap();
+        let red: Vec<u8> = texels.chunks_exact(4).map(|texel| texel[0]).collect();
+        assert_eq!(red, [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let tight: Vec<u8> = (1..=8).collect();
+        let texels = decoder_for(ddsfmt::formats::R8_UNORM)
+            .unwrap()
+            .decode(&tight, r8_volume(None, None))
+            .unwrap();
+        let red: Vec<u8> = texels.chunks_exact(4).map(|texel| texel[0]).collect();
+        assert_eq!(red, tight);
+    }
+
+    #[test]
+    fn rejects_small_pitches() {
+        let decoder = decoder_for(ddsfmt::formats::R8_UNORM).unwrap();
+        let bytes = [0; 64];
+        assert!(matches!(
+            decoder.decode(&bytes, r8_volume(Some(1), None)),
+            Err(DecoderError::SizeMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+        assert!(matches!(
+            decoder.decode(&bytes, r8_volume(Some(3), Some(5))),
+            Err(DecoderError::SizeMismatch {
+                expected: 6,
+                actual: 5
+            })
+        ));
+
+        let surface = SurfaceDescriptor {
+            row_pitch: Some(15),
+            ..SurfaceDescriptor::new(5, 5)
+        };
+        assert!(matches!(
+            decoder_for(ddsfmt::formats::BC1_UNORM)
+                .unwrap()
+                .decode(&bytes, surface),
+            Err(DecoderError::SizeMismatch {
+                expected: 16,
+                actual: 15
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        let decoder = decoder_for(ddsfmt::formats::R8_UNORM).unwrap();
+        assert!(matches!(
+            decoder.decode(&[0; 3], SurfaceDescriptor::new(2, 2)),
+            Err(DecoderError::TruncatedInput {
+                expected: 4,
+                actual: 3
+            })
+        ));
+        assert!(matches!(
+            decoder.decode(&[0; 12], r8_volume(Some(3), Some(8))),
+            Err(DecoderError::TruncatedInput {
+                expected: 13,
+                actual: 12
+            })
+        ));
+        assert!(matches!(
+            decoder_for(ddsfmt::formats::BC1_UNORM)
+                .unwrap()
+                .decode(&[0; 31], SurfaceDescriptor::new(5, 5)),
+            Err(DecoderError::TruncatedInput {
+                expected: 32,
+                actual: 31
+            })
+        ));
+    }
+
+    #[test]
+    fn decodes_zero_sized_surfaces() {
+        let decoder = decoder_for(ddsfmt::formats::BC1_UNORM).unwrap();
+        for surface in [
+            SurfaceDescriptor::new(0, 4),
+            SurfaceDescriptor::new(4, 0),
+            SurfaceDescriptor {
+                depth: 0,
+                ..SurfaceDescriptor::new(4, 4)
+            },
+        ] {
+            assert_eq!(decoder.decode(&[], surface).unwrap(), []);
+            assert!(matches!(
+                decoder.decode_at_pixel(&[], surface, (0, 0)),
+                Err(DecoderError::OutOfBounds(0, 0))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_pixels_outside_of_the_surface() {
+        let decoder = decoder_for(ddsfmt::formats::BC1_UNORM).unwrap();
+        let bytes = [0; 32];
+        let surface = SurfaceDescriptor::new(5, 5);
+        assert!(decoder.decode_at_pixel(&bytes, surface, (4, 4)).is_ok());
+        assert!(matches!(
+            decoder.decode_at_pixel(&bytes, surface, (5, 0)),
+            Err(DecoderError::OutOfBounds(5, 0))
+        ));
+        assert!(matches!(
+            decoder.decode_at_pixel(&bytes, surface, (0, 5)),
+            Err(DecoderError::OutOfBounds(0, 5))
+        ));
+    }
+
+    #[test]
+    fn crops_decoded_edge_blocks() {
+        let mut bytes = Vec::new();
+        for color in [0xF800, 0x07E0, 0x001F, 0xFFFF] {
+            bytes.extend_from_slice(&solid_bc1(color));
+        }
+        let surface = SurfaceDescriptor::new(5, 6);
+        let decoder = decoder_for(ddsfmt::formats::BC1_UNORM).unwrap();
+
+        let block = decoder.decode_at_pixel(&bytes, surface, (4, 5)).unwrap();
+        assert_eq!(block.origin(), (4, 4));
+        assert_eq!(block.size(), (4, 4));
+        assert_eq!(block.valid_size(), (1, 2));
+        assert_eq!(block.texels().len(), 16);
+        assert_eq!(block.texel(0, 1), [255, 255, 255, 255]);
+        assert_eq!(block.texel_f32(0, 1), [1.0; 4]);
+        assert_eq!(block.pixel_f32(4, 5), Some([1.0; 4]));
+        assert_eq!(block.pixel_f32(4, 6), None);
+        assert_eq!(block.pixel_f32(5, 4), None);
+        assert_eq!(block.pixel_f32(3, 4), None);
+
+        let block = decoder.decode_at_pixel(&bytes, surface, (2, 1)).unwrap();
+        assert_eq!(block.origin(), (0, 0));
+        assert_eq!(block.valid_size(), (4, 4));
+        assert_eq!(block.texel(3, 3), [255, 0, 0, 255]);
+        assert_eq!(block.pixel_f32(3, 3), Some([1.0, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn clamps_texels_read_as_rgba8() {
+        let mut block = DecodedBlock::empty(SurfaceDescriptor::new(1, 1), (1, 1), (0, 0));
+        block.texels[0] = [2.0, -1.0, 0.5, 1.0];
+        assert_eq!(block.texel(0, 0), [255, 0, 128, 255]);
+        assert_eq!(block.texel_f32(0, 0), [2.0, -1.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "outside of the block")]
+    fn panics_on_texels_outside_of_the_block() {
+        let block = DecodedBlock::empty(SurfaceDescriptor::new(5, 5), (4, 4), (4, 4));
+        block.texel_f32(4, 0);
+    }
+
+    /// Runs `encode_block_surface` over a 5x3 surface with 4x4 blocks storing the texels they are
+    /// given, returning those texels.
+    fn encoded_texels(input: EncoderInput, row_pitch: Option<usize>) -> Vec<[f32; 4]> {
+        let surface = SurfaceDescriptor {
+            row_pitch,
+            ..SurfaceDescriptor::new(5, 3)
+        };
+        let mut buffer = Vec::new();
+        let layout = BlockLayout::new(4, 4, 16 * 16);
+        encode_block_surface(input, surface, layout, &mut buffer, |texels, block| {
+            for (texel, out) in texels.iter().flatten().zip(block.chunks_exact_mut(4)) {
+                out.copy_from_slice(&texel.to_ne_bytes());
+            }
+        })
+        .unwrap();
+        buffer
+            .chunks_exact(16)
+            .map(|texel| {
+                std::array::from_fn(|c| {
+                    let bytes = &texel[c * 4..c * 4 + 4];
+                    f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+                })
+            })
+            .collect()
+    }
+
+    /// The texels of both blocks of a 5x3 surface, repeating its last column and row.
+    fn padded_texels(pixel: impl Fn(usize, usize) -> [f32; 4]) -> Vec<[f32; 4]> {
+        (0..2)
+            .flat_map(|block_x| (0..16).map(move |i| (block_x * 4 + i % 4, i / 4)))
+            .map(|(x, y)| pixel(x.min(4), y.min(2)))
+            .collect()
+    }
+
+    #[test]
+    fn pads_rgba8_input_with_edge_texels() {
+        let pixel = |x: usize, y: usize| [x as u8 * 50, y as u8 * 100, 7, 200];
+        let mut bytes = Vec::new();
+        let mut padded = Vec::new();
+        for y in 0..3 {
+            for x in 0..5 {
+                bytes.extend_from_slice(&pixel(x, y));
+            }
+            padded.extend_from_slice(&bytes[bytes.len() - 20..]);
+            padded.extend_from_slice(&[0xEE; 4]);
+        }
+        let expected = padded_texels(|x, y| pixel(x, y).map(|v| v as f32 / 255.0));
+        assert_eq!(encoded_texels(EncoderInput::Rgba8(&bytes), None), expected);
+        assert_eq!(
+            encoded_texels(EncoderInput::Rgba8(&padded), Some(24)),
+            expected
+        );
+    }
+
+    #[test]
+    fn pads_16_bit_input_with_edge_texels() {
+        let values: Vec<u16> = (0..15)
+            .flat_map(|i| [(i % 5) as u16 * 1000, (i / 5) as u16 * 2000])
+            .collect();
+        let expected = padded_texels(|x, y| {
+            [
+                (x * 1000) as f32 / 65535.0,
+                (y * 2000) as f32 / 65535.0,
+                0.0,
+                1.0,
+            ]
+        });
+        assert_eq!(encoded_texels(EncoderInput::Rg16(&values), None), expected);
+    }
+
+    #[test]
+    fn pads_float_input_with_edge_texels() {
+        let pixel = |x: usize, y: usize| [x as f32, y as f32, -1.0, 0.5];
+        let values: Vec<f32> = (0..15).flat_map(|i| pixel(i % 5, i / 5)).collect();
+        let texels = encoded_texels(EncoderInput::Rgba32F(&values), None);
+        assert_eq!(texels, padded_texels(pixel));
+
+        let values: Vec<f32> = (0..15).map(|i| (i % 5 + i / 5 * 10) as f32).collect();
+        let texels = encoded_texels(EncoderInput::R32F(&values), None);
+        assert_eq!(
+            texels,
+            padded_texels(|x, y| [(x + y * 10) as f32, 0.0, 0.0, 1.0])
+        );
+    }
 }
@@ -0,0 +1,429 @@
+use crate::{
+    decode_block_at, decode_block_surface,
+    yuv::{yuv_to_rgb, ColorMatrix, ColorRange},
+    BlockLayout, Blocks, ChannelType, DecodedBlock, Decoder, DecoderResult, SurfaceDescriptor,
+    TexelFormat,
+};
+use byteorder::{ByteOrder, LE};
+
+/// Each 32-bit block holds a horizontal pair of pixels.
+pub(crate) const PACKED_422_LAYOUT: BlockLayout = BlockLayout::new(2, 1, 4);
+
+/// Packed formats storing a pair of pixels in 32 bits, sharing two of their three channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Packed422Format {
+    /// Bytes `R, G0, B, G1`, with red and blue shared by both pixels.
+    R8G8B8G8,
+    /// Bytes `G0, R, G1, B`, with red and blue shared by both pixels.
+    G8R8G8B8,
+    /// Bytes `Y0, U, Y1, V`, with chroma shared by both pixels.
+    YUY2,
+    /// Bytes `U, Y0, V, Y1`, with chroma shared by both pixels.
+    UYVY,
+}
+
+/// Decoder for packed 4:2:2 surfaces, producing RGBA8 texels.
+///
+/// YUV formats are converted with the BT.601 matrix over the limited range by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Packed422Decoder {
+    format: Packed422Format,
+    matrix: ColorMatrix,
+    range: ColorRange,
+}
+
+impl Packed422Decoder {
+    pub fn new(format: Packed422Format) -> Packed422Decoder {
+        Packed422Decoder {
+            format,
+            matrix: ColorMatrix::default(),
+            range: ColorRange::default(),
+        }
+    }
+
+    /// Sets the color matrix used to convert YUV formats. Ignored by RGB formats.
+    pub fn color_matrix(mut self, matrix: ColorMatrix) -> Packed422Decoder {
+        self.matrix = matrix;
+        self
+    }
+
+    /// Sets the range of the samples of YUV formats. Ignored by RGB formats.
+    pub fn color_range(mut self, range: ColorRange) -> Packed422Decoder {
+        self.range = range;
+        self
+    }
+
+    pub fn format(&self) -> Packed422Format {
+        self.format
+    }
+
+    fn decode_block(&self, block: &[u8], texels: &mut [u8]) {
+        let (first, second) = match self.format {
+            Packed422Format::R8G8B8G8 => (
+                [block[0], block[1], block[2]],
+                [block[0], block[3], block[2]],
+            ),
+            Packed422Format::G8R8G8B8 => (
+                [block[1], block[0], block[3]],
+                [block[1], block[2], block[3]],
+            ),
+            Packed422Format::YUY2 => (
+                self.yuv_to_rgb8(block[0], block[1], block[3]),
+                self.yuv_to_rgb8(block[2], block[1], block[3]),
+            ),
+            Packed422Format::UYVY => (
+                self.yuv_to_rgb8(block[1], block[0], block[2]),
+                self.yuv_to_rgb8(block[3], block[0], block[2]),
+            ),
+        };
+        texels[0..3].copy_from_slice(&first);
+        texels[3] = 255;
+        texels[4..7].copy_from_slice(&second);
+        texels[7] = 255;
+    }
+
+    fn yuv_to_rgb8(&self, y: u8, u: u8, v: u8) -> [u8; 3] {
+        let rgb = yuv_to_rgb((y as u32, u as u32, v as u32), 8, self.matrix, self.range);
+        [
+            (rgb[0] * 255.0).round() as u8,
+            (rgb[1] * 255.0).round() as u8,
+            (rgb[2] * 255.0).round() as u8,
+        ]
+    }
+}
+
+impl Decoder for Packed422Decoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(
+            bytes,
+            surface,
+            PACKED_422_LAYOUT,
+            4,
+            buffer,
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            PACKED_422_LAYOUT,
+            TexelFormat::RGBA8,
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, PACKED_422_LAYOUT)
+    }
+}
+
+/// Packed YUV formats storing 8, 10 or 16-bit samples, either per pixel or shared by a pair of
+/// pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PackedYuvFormat {
+    /// 8-bit 4:4:4, bytes `V, U, Y, A`.
+    AYUV,
+    /// 10-bit 4:4:4 with 2-bit alpha, a 32-bit little-endian word with `U` in the lowest bits,
+    /// followed by `Y`, `V` and `A`.
+    Y410,
+    /// 16-bit 4:4:4, little-endian words `U, Y, V, A`.
+    Y416,
+    /// 10-bit 4:2:2, little-endian words `Y0, U, Y1, V`, with samples in the high 10 bits.
+    Y210,
+    /// 16-bit 4:2:2, little-endian words `Y0, U, Y1, V`.
+    Y216,
+}
+
+impl PackedYuvFormat {
+    fn layout(self) -> BlockLayout {
+        match self {
+            PackedYuvFormat::AYUV | PackedYuvFormat::Y410 => BlockLayout::new(1, 1, 4),
+            PackedYuvFormat::Y416 => BlockLayout::new(1, 1, 8),
+            PackedYuvFormat::Y210 | PackedYuvFormat::Y216 => BlockLayout::new(2, 1, 8),
+        }
+    }
+}
+
+/// Decoder for packed high bit depth YUV surfaces, producing RGBA texels.
+///
+/// Samples are converted with the BT.601 matrix over the limited range and written as
+/// [`ChannelType::Norm16`] by default. Formats without alpha decode as opaque.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedYuvDecoder {
+    format: PackedYuvFormat,
+    matrix: ColorMatrix,
+    range: ColorRange,
+    channel_type: ChannelType,
+}
+
+impl PackedYuvDecoder {
+    pub fn new(format: PackedYuvFormat) -> PackedYuvDecoder {
+        PackedYuvDecoder {
+            format,
+            matrix: ColorMatrix::default(),
+            range: ColorRange::default(),
+            channel_type: ChannelType::Norm16,
+        }
+    }
+
+    /// Sets the color matrix used to convert samples to RGB.
+    pub fn color_matrix(mut self, matrix: ColorMatrix) -> PackedYuvDecoder {
+        self.matrix = matrix;
+        self
+    }
+
+    /// Sets the range of the encoded samples.
+    pub fn color_range(mut self, range: ColorRange) -> PackedYuvDecoder {
+        self.range = range;
+        self
+    }
+
+    /// Sets the storage of each decoded channel.
+    pub fn channel_type(mut self, channel_type: ChannelType) -> PackedYuvDecoder {
+        self.channel_type = channel_type;
+        self
+    }
+
+    pub fn format(&self) -> PackedYuvFormat {
+        self.format
+    }
+
+    fn decode_block(&self, block: &[u8], texels: &mut [u8]) {
+        let word = |i: usize| LE::read_u16(&block[i * 2..]) as u32;
+        let mut pixels = [((0, 0, 0), 1.0); 2];
+        let (count, bits) = match self.format {
+            PackedYuvFormat::AYUV => {
+                let yuv = (block[2] as u32, block[1] as u32, block[0] as u32);
+                pixels[0] = (yuv, block[3] as f32 / 255.0);
+                (1, 8)
+            }
+            PackedYuvFormat::Y410 => {
+                let packed = LE::read_u32(block);
+                let yuv = (
+                    (packed >> 10) & 0x3FF,
+                    packed & 0x3FF,
+                    (packed >> 20) & 0x3FF,
+                );
+                pixels[0] = (yuv, (packed >> 30) as f32 / 3.0);
+                (1, 10)
+            }
+            PackedYuvFormat::Y416 => {
+                pixels[0] = ((word(1), word(0), word(2)), word(3) as f32 / 65535.0);
+                (1, 16)
+            }
+            PackedYuvFormat::Y210 => {
+                let (u, v) = (word(1) >> 6, word(3) >> 6);
+                pixels[0].0 = (word(0) >> 6, u, v);
+                pixels[1].0 = (word(2) >> 6, u, v);
+                (2, 10)
+            }
+            PackedYuvFormat::Y216 => {
+                pixels[0].0 = (word(0), word(1), word(3));
+                pixels[1].0 = (word(2), word(1), word(3));
+                (2, 16)
+            }
+        };
+
+        let size = self.channel_type.size();
+        for ((yuv, alpha), texel) in pixels
+            .iter()
+            .take(count)
+            .zip(texels.chunks_exact_mut(size * 4))
+        {
+            let rgb = yuv_to_rgb(*yuv, bits, self.matrix, self.range);
+            for (value, out) in rgb
+                .iter()
+                .chain(&[*alpha])
+                .zip(texel.chunks_exact_mut(size))
+            {
+                self.channel_type.write(*value, false, out);
+            }
+        }
+    }
+}
+
+impl Decoder for PackedYuvDecoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(
+            bytes,
+            surface,
+            self.format.layout(),
+            self.channel_type.size() * 4,
+            buffer,
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            self.format.layout(),
+            TexelFormat::new(4, self.channel_type, false),
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, self.format.layout())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_422(format: Packed422Format, bytes: &[u8], width: usize) -> Vec<u8> {
+        Packed422Decoder::new(format)
+            .color_range(ColorRange::Full)
+            .decode(bytes, SurfaceDescriptor::new(width, 1))
+            .unwrap()
+    }
+
+    #[test]
+    fn decodes_rgb_422_byte_order() {
+        let expected = [10, 20, 30, 255, 10, 40, 30, 255];
+        let texels = decode_422(Packed422Format::R8G8B8G8, &[10, 20, 30, 40], 2);
+        assert_eq!(texels, expected);
+        let texels = decode_422(Packed422Format::G8R8G8B8, &[20, 10, 40, 30], 2);
+        assert_eq!(texels, expected);
+    }
+
+    #[test]
+    fn decodes_yuv_422_byte_order() {
+        // The same full red chroma over black and white luma
+        let expected = [178, 0, 0, 255, 255, 164, 255, 255];
+        let texels = decode_422(Packed422Format::YUY2, &[0, 128, 255, 255], 2);
+        assert_eq!(texels, expected);
+        let texels = decode_422(Packed422Format::UYVY, &[128, 0, 255, 255], 2);
+        assert_eq!(texels, expected);
+
+        // Full blue chroma
+        let texels = decode_422(Packed422Format::YUY2, &[0, 255, 0, 128], 2);
+        assert_eq!(texels, [0, 0, 225, 255, 0, 0, 225, 255]);
+    }
+
+    #[test]
+    fn decodes_limited_range_yuv_422() {
+        let texels = Packed422Decoder::new(Packed422Format::YUY2)
+            .decode(&[16, 128, 235, 128], SurfaceDescriptor::new(2, 1))
+            .unwrap();
+        assert_eq!(texels, [0, 0, 0, 255, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn crops_last_pair_of_odd_widths() {
+        let texels = decode_422(Packed422Format::R8G8B8G8, &[1, 2, 3, 4, 5, 6, 7, 8], 3);
+        assert_eq!(texels, [1, 2, 3, 255, 1, 4, 3, 255, 5, 6, 7, 255]);
+    }
+
+    fn decode_yuv(format: PackedYuvFormat, range: ColorRange, bytes: &[u8]) -> Vec<u8> {
+        let width = format.layout().width;
+        PackedYuvDecoder::new(format)
+            .color_range(range)
+            .channel_type(ChannelType::Norm8)
+            .decode(bytes, SurfaceDescriptor::new(width, 1))
+            .unwrap()
+    }
+
+    fn words(samples: &[u16]) -> Vec<u8> {
+        samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn decodes_ayuv() {
+        let texels = decode_yuv(
+            PackedYuvFormat::AYUV,
+            ColorRange::Limited,
+            &[128, 128, 235, 128],
+        );
+        assert_eq!(texels, [255, 255, 255, 128]);
+        let texels = decode_yuv(PackedYuvFormat::AYUV, ColorRange::Full, &[255, 128, 0, 255]);
+        assert_eq!(texels, [178, 0, 0, 255]);
+    }
+
+    #[test]
+    fn decodes_y410() {
+        let pack = |y: u32, u: u32, v: u32, a: u32| (u | y << 10 | v << 20 | a << 30).to_le_bytes();
+        let texels = decode_yuv(
+            PackedYuvFormat::Y410,
+            ColorRange::Limited,
+            &pack(940, 512, 512, 2),
+        );
+        assert_eq!(texels, [255, 255, 255, 170]);
+        let texels = decode_yuv(
+            PackedYuvFormat::Y410,
+            ColorRange::Full,
+            &pack(0, 512, 1023, 3),
+        );
+        assert_eq!(texels, [179, 0, 0, 255]);
+    }
+
+    #[test]
+    fn decodes_y416() {
+        let bytes = words(&[1 << 15, 235 << 8, 1 << 15, 65535]);
+        let texels = PackedYuvDecoder::new(PackedYuvFormat::Y416)
+            .decode(&bytes, SurfaceDescriptor::new(1, 1))
+            .unwrap();
+        assert_eq!(texels, words(&[65535; 4]));
+
+        let bytes = words(&[1 << 15, 0, 65535, 0]);
+        let texels = decode_yuv(PackedYuvFormat::Y416, ColorRange::Full, &bytes);
+        assert_eq!(texels, [179, 0, 0, 0]);
+    }
+
+    #[test]
+    fn decodes_y210() {
+        // Noise in the unused low bits is ignored
+        let bytes = words(&[64 << 6 | 0x3F, 512 << 6, 940 << 6, 512 << 6 | 0x3F]);
+        let texels = decode_yuv(PackedYuvFormat::Y210, ColorRange::Limited, &bytes);
+        assert_eq!(texels, [0, 0, 0, 255, 255, 255, 255, 255]);
+
+        let bytes = words(&[0, 512 << 6, 0, 1023 << 6]);
+        let texels = decode_yuv(PackedYuvFormat::Y210, ColorRange::Full, &bytes);
+        assert_eq!(texels, [179, 0, 0, 255, 179, 0, 0, 255]);
+    }
+
+    #[test]
+    fn decodes_y216() {
+        let bytes = words(&[16 << 8, 1 << 15, 235 << 8, 1 << 15]);
+        let texels = decode_yuv(PackedYuvFormat::Y216, ColorRange::Limited, &bytes);
+        assert_eq!(texels, [0, 0, 0, 255, 255, 255, 255, 255]);
+
+        let bytes = words(&[0, 1 << 15, 0, 65535]);
+        let texels = decode_yuv(PackedYuvFormat::Y216, ColorRange::Full, &bytes);
+        assert_eq!(texels, [179, 0, 0, 255, 179, 0, 0, 255]);
+    }
+}
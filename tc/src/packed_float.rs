@@ -0,0 +1,160 @@
+use crate::{
+    decode_block_at, decode_block_surface,
+    float::{rgb9e5_to_f32, unsigned_float_to_f32},
+    BlockLayout, Blocks, ChannelType, DecodedBlock, Decoder, DecoderResult, SurfaceDescriptor,
+    TexelFormat,
+};
+use byteorder::{ByteOrder, LE};
+
+pub(crate) const PACKED_FLOAT_LAYOUT: BlockLayout = BlockLayout::new(1, 1, 4);
+
+/// High dynamic range formats packing three or four channels into 32 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PackedFloatFormat {
+    /// Three 9-bit mantissas sharing a 5-bit exponent, `R9G9B9E5_SHAREDEXP`.
+    R9G9B9E5SharedExp,
+    /// Unsigned floats with 6-bit mantissas for red and green and a 5-bit mantissa for blue,
+    /// `R11G11B10_FLOAT`.
+    R11G11B10Float,
+    /// 10-bit extended range color with a 2-bit alpha, `R10G10B10_XR_BIAS_A2_UNORM`.
+    R10G10B10XrBiasA2,
+}
+
+/// Decoder for packed float surfaces, producing RGBA texels of native-endian `f32`.
+///
+/// Conversions are exact, preserving denormals, infinities and NaNs. Formats without alpha decode
+/// as opaque.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PackedFloatDecoder {
+    format: PackedFloatFormat,
+}
+
+impl PackedFloatDecoder {
+    pub fn new(format: PackedFloatFormat) -> PackedFloatDecoder {
+        PackedFloatDecoder { format }
+    }
+
+    pub fn format(&self) -> PackedFloatFormat {
+        self.format
+    }
+
+    fn decode_block(&self, pixel: &[u8], texel: &mut [u8]) {
+        let values = decode_packed_float(self.format, LE::read_u32(pixel));
+        for (value, out) in values.iter().zip(texel.chunks_exact_mut(4)) {
+            out.copy_from_slice(&value.to_ne_bytes());
+        }
+    }
+}
+
+impl Decoder for PackedFloatDecoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(
+            bytes,
+            surface,
+            PACKED_FLOAT_LAYOUT,
+            16,
+            buffer,
+            |pixel, texel| self.decode_block(pixel, texel),
+        )
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            PACKED_FLOAT_LAYOUT,
+            TexelFormat::new(4, ChannelType::Float32, false),
+            |pixel, texel| self.decode_block(pixel, texel),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, PACKED_FLOAT_LAYOUT)
+    }
+}
+
+/// Decodes a 32-bit packed float pixel into RGBA values.
+pub(crate) fn decode_packed_float(format: PackedFloatFormat, packed: u32) -> [f32; 4] {
+    match format {
+        PackedFloatFormat::R9G9B9E5SharedExp => {
+            let [r, g, b] = rgb9e5_to_f32(packed);
+            [r, g, b, 1.0]
+        }
+        PackedFloatFormat::R11G11B10Float => [
+            unsigned_float_to_f32(packed, 6),
+            unsigned_float_to_f32(packed >> 11, 6),
+            unsigned_float_to_f32(packed >> 22, 5),
+            1.0,
+        ],
+        PackedFloatFormat::R10G10B10XrBiasA2 => {
+            // Extended range values are biased by 0x180 and scaled by 510, spanning about
+            // [-0.75, 1.25]
+            let xr = |value: u32| ((value & 0x3FF) as f32 - 384.0) / 510.0;
+            [
+                xr(packed),
+                xr(packed >> 10),
+                xr(packed >> 20),
+                (packed >> 30) as f32 / 3.0,
+            ]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rgb9e5_covers_exponent_range() {
+        // Exponent 0 holds the smallest values, a mantissa of 1 being 2^-24
+        let smallest = 1 | (2 << 9) | (511 << 18);
+        assert_eq!(
+            decode_packed_float(PackedFloatFormat::R9G9B9E5SharedExp, smallest),
+            [2f32.powi(-24), 2f32.powi(-23), 511.0 * 2f32.powi(-24), 1.0]
+        );
+        let largest = 511 | (256 << 9) | (31 << 27);
+        assert_eq!(
+            decode_packed_float(PackedFloatFormat::R9G9B9E5SharedExp, largest),
+            [65408.0, 32768.0, 0.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn r11g11b10_preserves_special_values() {
+        let denormal = 1 | (63 << 11) | (1 << 22);
+        assert_eq!(
+            decode_packed_float(PackedFloatFormat::R11G11B10Float, denormal),
+            [2f32.powi(-20), 63.0 * 2f32.powi(-20), 2f32.powi(-19), 1.0]
+        );
+        let special = (31 << 6) | ((31 << 6 | 1) << 11) | (30 << 5 | 31) << 22;
+        let [r, g, b, a] = decode_packed_float(PackedFloatFormat::R11G11B10Float, special);
+        assert_eq!(r, f32::INFINITY);
+        assert!(g.is_nan());
+        assert_eq!(b, 64512.0);
+        assert_eq!(a, 1.0);
+    }
+
+    #[test]
+    fn xr_bias_centers_on_zero() {
+        let packed = 0x180 | (0x3FF << 10) | (3 << 30);
+        assert_eq!(
+            decode_packed_float(PackedFloatFormat::R10G10B10XrBiasA2, packed),
+            [0.0, 639.0 / 510.0, -384.0 / 510.0, 1.0]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{
+    decode_block_at, decode_block_surface, BlockLayout, Blocks, DecodedBlock, Decoder,
+    DecoderResult, SurfaceDescriptor, TexelFormat,
+};
+
+/// Color table indexed by palettized formats, holding up to 256 RGBA8 entries.
+///
+/// Indices past the end of the palette decode to transparent black.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Palette {
+    entries: Vec<[u8; 4]>,
+}
+
+impl Palette {
+    /// Creates a palette from RGBA8 entries. Entries past the first 256 are ignored.
+    pub fn new(mut entries: Vec<[u8; 4]>) -> Palette {
+        entries.truncate(256);
+        Palette { entries }
+    }
+
+    /// Reads a palette from tightly packed RGBA8 entries, such as the contents of a raw palette
+    /// file. Trailing bytes not forming a whole entry are ignored.
+    pub fn from_rgba8(bytes: &[u8]) -> Palette {
+        Palette::new(
+            bytes
+                .chunks_exact(4)
+                .map(|c| [c[0], c[1], c[2], c[3]])
+                .collect(),
+        )
+    }
+
+    /// Reads a palette from tightly packed RGB8 entries, such as the contents of an `.act` file.
+    /// Entries are opaque. Trailing bytes not forming a whole entry are ignored.
+    pub fn from_rgb8(bytes: &[u8]) -> Palette {
+        Palette::new(
+            bytes
+                .chunks_exact(3)
+                .map(|c| [c[0], c[1], c[2], 255])
+                .collect(),
+        )
+    }
+
+    pub fn entries(&self) -> &[[u8; 4]] {
+        &self.entries
+    }
+
+    fn get(&self, index: u8) -> Option<[u8; 4]> {
+        self.entries.get(index as usize).copied()
+    }
+}
+
+/// Palettized formats, storing an index into a [`Palette`] for each pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaletteFormat {
+    /// 8-bit index, with alpha taken from the palette.
+    P8,
+    /// 16-bit little-endian pixels with the index in the low byte and alpha in the high byte.
+    A8P8,
+    /// 4-bit alpha in the high nibble and 4-bit index in the low nibble.
+    AI44,
+    /// 4-bit index in the high nibble and 4-bit alpha in the low nibble.
+    IA44,
+}
+
+impl PaletteFormat {
+    fn layout(self) -> BlockLayout {
+        match self {
+            PaletteFormat::A8P8 => BlockLayout::new(1, 1, 2),
+            _ => BlockLayout::new(1, 1, 1),
+        }
+    }
+}
+
+/// Decoder for palettized surfaces, producing RGBA8 texels.
+///
+/// Formats storing their own alpha replace the alpha of the palette entries. Indices past the end
+/// of the palette decode to transparent black whatever their alpha.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PaletteDecoder {
+    format: PaletteFormat,
+    palette: Palette,
+}
+
+impl PaletteDecoder {
+    pub fn new(format: PaletteFormat, palette: Palette) -> PaletteDecoder {
+        PaletteDecoder { format, palette }
+    }
+
+    pub fn format(&self) -> PaletteFormat {
+        self.format
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    fn decode_pixel(&self, pixel: &[u8]) -> [u8; 4] {
+        let (index, alpha) = match self.format {
+            PaletteFormat::P8 => (pixel[0], None),
+            PaletteFormat::A8P8 => (pixel[0], Some(pixel[1])),
+            PaletteFormat::AI44 => (pixel[0] & 0xF, Some((pixel[0] >> 4) * 17)),
+            PaletteFormat::IA44 => (pixel[0] >> 4, Some((pixel[0] & 0xF) * 17)),
+        };
+        match (self.palette.get(index), alpha) {
+            (Some([r, g, b, _]), Some(alpha)) => [r, g, b, alpha],
+            (Some(texel), None) => texel,
+            (None, _) => [0; 4],
+        }
+    }
+}
+
+impl Decoder for PaletteDecoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(
+            bytes,
+            surface,
+            self.format.layout(),
+            4,
+            buffer,
+            |pixel, texel| texel.copy_from_slice(&self.decode_pixel(pixel)),
+        )
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            self.format.layout(),
+            TexelFormat::RGBA8,
+            |pixel, texel| texel.copy_from_slice(&self.decode_pixel(pixel)),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, self.format.layout())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(format: PaletteFormat, bytes: &[u8], width: usize) -> Vec<u8> {
+        let palette = Palette::from_rgb8(&[10, 20, 30, 40, 50, 60, 70, 80, 90]);
+        PaletteDecoder::new(format, palette)
+            .decode(bytes, SurfaceDescriptor::new(width, 1))
+            .unwrap()
+    }
+
+    #[test]
+    fn decodes_p8() {
+        let texels = decode(PaletteFormat::P8, &[2, 0], 2);
+        assert_eq!(texels, [70, 80, 90, 255, 10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn decodes_a8p8() {
+        let texels = decode(PaletteFormat::A8P8, &[1, 0x80, 2, 0x00], 2);
+        assert_eq!(texels, [40, 50, 60, 0x80, 70, 80, 90, 0x00]);
+    }
+
+    #[test]
+    fn decodes_nibble_order() {
+        let texels = decode(PaletteFormat::AI44, &[0x51, 0x12], 2);
+        assert_eq!(texels, [40, 50, 60, 0x55, 70, 80, 90, 0x11]);
+        let texels = decode(PaletteFormat::IA44, &[0x15, 0x21], 2);
+        assert_eq!(texels, [40, 50, 60, 0x55, 70, 80, 90, 0x11]);
+    }
+
+    #[test]
+    fn decodes_out_of_range_indices_as_transparent_black() {
+        assert_eq!(decode(PaletteFormat::P8, &[3], 1), [0; 4]);
+        assert_eq!(decode(PaletteFormat::A8P8, &[255, 0xFF], 1), [0; 4]);
+        assert_eq!(decode(PaletteFormat::AI44, &[0xF3], 1), [0; 4]);
+        assert_eq!(decode(PaletteFormat::IA44, &[0xFF], 1), [0; 4]);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{
+    check_len, check_pitch, required_len,
+    yuv::{yuv_to_rgb, ColorMatrix, ColorRange},
+    Blocks, ChannelType, DecodedBlock, Decoder, DecoderError, DecoderResult, SurfaceDescriptor,
+    TexelFormat,
+};
+use byteorder::{ByteOrder, LE};
+use ddsfmt::{CompressedFormat, TextureFormat};
+
+/// Planar YUV formats, storing a luma plane followed by a plane of interleaved U and V samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlanarYuvFormat {
+    /// 8-bit 4:2:0, with chroma subsampled horizontally and vertically.
+    NV12,
+    /// 8-bit 4:1:1, with chroma subsampled horizontally by 4.
+    NV11,
+    /// 10-bit 4:2:0, stored in the high bits of 16-bit little-endian samples.
+    P010,
+    /// 16-bit 4:2:0.
+    P016,
+}
+
+impl PlanarYuvFormat {
+    /// Horizontal and vertical chroma subsampling factors.
+    fn subsampling(self) -> (usize, usize) {
+        match self {
+            PlanarYuvFormat::NV11 => (4, 1),
+            _ => (2, 2),
+        }
+    }
+
+    fn sample_size(self) -> usize {
+        match self {
+            PlanarYuvFormat::NV12 | PlanarYuvFormat::NV11 => 1,
+            PlanarYuvFormat::P010 | PlanarYuvFormat::P016 => 2,
+        }
+    }
+
+    fn texture_format(self) -> TextureFormat {
+        TextureFormat::Compressed(match self {
+            PlanarYuvFormat::NV12 => CompressedFormat::NV12,
+            PlanarYuvFormat::NV11 => CompressedFormat::NV11,
+            PlanarYuvFormat::P010 => CompressedFormat::P010,
+            PlanarYuvFormat::P016 => CompressedFormat::P016,
+        })
+    }
+
+    fn sample_bits(self) -> u32 {
+        match self {
+            PlanarYuvFormat::NV12 | PlanarYuvFormat::NV11 => 8,
+            PlanarYuvFormat::P010 => 10,
+            PlanarYuvFormat::P016 => 16,
+        }
+    }
+}
+
+/// Byte offsets of the planes of a surface.
+struct PlaneLayout {
+    luma_pitch: usize,
+    chroma_pitch: usize,
+    /// Offset of the chroma plane from the start of a slice.
+    chroma_offset: usize,
+    slice_pitch: usize,
+    /// Number of bytes needed to hold every slice.
+    required: usize,
+}
+
+/// Decoder for planar YUV surfaces, producing RGBA texels.
+///
+/// The chroma plane directly follows the luma plane, and both share the row pitch of the surface
+/// if it has one. Chroma samples are replicated across the pixels they cover. Samples are converted
+/// with the BT.601 matrix over the limited range and written as [`ChannelType::Norm8`] by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlanarYuvDecoder {
+    format: PlanarYuvFormat,
+    matrix: ColorMatrix,
+    range: ColorRange,
+    channel_type: ChannelType,
+}
+
+impl PlanarYuvDecoder {
+    pub fn new(format: PlanarYuvFormat) -> PlanarYuvDecoder {
+        PlanarYuvDecoder {
+            format,
+            matrix: ColorMatrix::default(),
+            range: ColorRange::default(),
+            channel_type: ChannelType::Norm8,
+        }
+    }
+
+    /// Sets the color matrix used to convert samples to RGB.
+    pub fn color_matrix(mut self, matrix: ColorMatrix) -> PlanarYuvDecoder {
+        self.matrix = matrix;
+        self
+    }
+
+    /// Sets the range of the encoded samples.
+    pub fn color_range(mut self, range: ColorRange) -> PlanarYuvDecoder {
+        self.range = range;
+        self
+    }
+
+    /// Sets the storage of each decoded channel.
+    pub fn channel_type(mut self, channel_type: ChannelType) -> PlanarYuvDecoder {
+        self.channel_type = channel_type;
+        self
+    }
+
+    pub fn format(&self) -> PlanarYuvFormat {
+        self.format
+    }
+
+    /// Computes where the planes of each slice of a surface are. When a row pitch is given, both
+    /// planes use it.
+    fn plane_layout(&self, surface: SurfaceDescriptor) -> DecoderResult<PlaneLayout> {
+        let (sub_x, sub_y) = self.format.subsampling();
+        let sample_size = self.format.sample_size();
+        let luma_row = surface.width * sample_size;
+        let chroma_row = surface.width.div_ceil(sub_x) * 2 * sample_size;
+        let chroma_rows = surface.height.div_ceil(sub_y);
+        let (luma_pitch, chroma_pitch) = match surface.row_pitch {
+            Some(_) => {
+                let pitch = check_pitch(surface.row_pitch, luma_row.max(chroma_row))?;
+                (pitch, pitch)
+            }
+            None => (luma_row, chroma_row),
+        };
+        let chroma_offset = luma_pitch * surface.height;
+        let slice_pitch = check_pitch(
+            surface.slice_pitch,
+            chroma_offset + chroma_pitch * chroma_rows,
+        )?;
+        let required = if surface.depth == 0 || luma_row == 0 || chroma_rows == 0 {
+            0
+        } else {
+            (surface.depth - 1) * slice_pitch
+                + chroma_offset
+                + required_len(1, 0, chroma_rows, chroma_pitch, chroma_row)
+        };
+        Ok(PlaneLayout {
+            luma_pitch,
+            chroma_pitch,
+            chroma_offset,
+            slice_pitch,
+            required,
+        })
+    }
+
+    fn sample(&self, bytes: &[u8], offset: usize) -> u32 {
+        match self.format {
+            PlanarYuvFormat::NV12 | PlanarYuvFormat::NV11 => bytes[offset] as u32,
+            PlanarYuvFormat::P010 => (LE::read_u16(&bytes[offset..]) >> 6) as u32,
+            PlanarYuvFormat::P016 => LE::read_u16(&bytes[offset..]) as u32,
+        }
+    }
+
+    /// Decodes the pixel at `(x, y)` of the slice starting at `slice`.
+    fn decode_pixel(
+        &self,
+        slice: &[u8],
+        planes: &PlaneLayout,
+        (x, y): (usize, usize),
+        texel: &mut [u8],
+    ) {
+        let (sub_x, sub_y) = self.format.subsampling();
+        let sample_size = self.format.sample_size();
+        let luma = self.sample(slice, y * planes.luma_pitch + x * sample_size);
+        let chroma = planes.chroma_offset
+            + (y / sub_y) * planes.chroma_pitch
+            + (x / sub_x) * 2 * sample_size;
+        let u = self.sample(slice, chroma);
+        let v = self.sample(slice, chroma + sample_size);
+
+        let rgb = yuv_to_rgb(
+            (luma, u, v),
+            self.format.sample_bits(),
+            self.matrix,
+            self.range,
+        );
+        let size = self.channel_type.size();
+        for (value, out) in rgb.iter().chain(&[1.0]).zip(texel.chunks_exact_mut(size)) {
+            self.channel_type.write(*value, false, out);
+        }
+    }
+}
+
+impl Decoder for PlanarYuvDecoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        let planes = self.plane_layout(surface)?;
+        check_len(bytes, planes.required)?;
+        let size = self.channel_type.size();
+        let width = surface.width.max(1);
+        let slice_texels = surface.width * surface.height;
+
+        buffer.clear();
+        buffer.resize(slice_texels * surface.depth * size * 4, 0);
+        for (i, texel) in buffer.chunks_exact_mut(size * 4).enumerate() {
+            let (x, y, z) = (i % width, (i % slice_texels) / width, i / slice_texels);
+            self.decode_pixel(&bytes[z * planes.slice_pitch..], &planes, (x, y), texel);
+        }
+        Ok(())
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        (x, y): (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        if x >= surface.width || y >= surface.height || surface.depth == 0 {
+            return Err(DecoderError::OutOfBounds(x, y));
+        }
+        let planes = self.plane_layout(surface)?;
+        check_len(bytes, planes.required)?;
+
+        // Blocks span the pixels sharing a chroma sample
+        let format = TexelFormat::new(4, self.channel_type, false);
+        let mut block = DecodedBlock::empty(surface, self.format.subsampling(), (x, y));
+        let mut texel = [0; 16];
+        let (origin, (width, _)) = (block.origin, block.size);
+        for by in 0..block.valid_size.1 {
+            for bx in 0..block.valid_size.0 {
+                let coords = (origin.0 + bx, origin.1 + by);
+                self.decode_pixel(bytes, &planes, coords, &mut texel[..format.size()]);
+                block.texels[by * width + bx] = format.read_rgba(&texel[..format.size()]);
+            }
+        }
+        Ok(block)
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        _bytes: &'a [u8],
+        _surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Err(DecoderError::UnsupportedFormat(
+            self.format.texture_format(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(
+        format: PlanarYuvFormat,
+        range: ColorRange,
+        bytes: &[u8],
+        (width, height): (usize, usize),
+    ) -> Vec<[u8; 4]> {
+        PlanarYuvDecoder::new(format)
+            .color_range(range)
+            .decode(bytes, SurfaceDescriptor::new(width, height))
+            .unwrap()
+            .chunks_exact(4)
+            .map(|texel| [texel[0], texel[1], texel[2], texel[3]])
+            .collect()
+    }
+
+    fn words(samples: &[u16]) -> Vec<u8> {
+        samples.iter().flat_map(|s| s.to_le_bytes()).collect()
+    }
+
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const RED: [u8; 4] = [178, 0, 0, 255];
+
+    #[test]
+    fn decodes_nv12_ranges() {
+        let bytes = [16, 235, 235, 16, 128, 128];
+        let texels = decode(PlanarYuvFormat::NV12, ColorRange::Limited, &bytes, (2, 2));
+        assert_eq!(texels, [BLACK, WHITE, WHITE, BLACK]);
+
+        let texels = decode(PlanarYuvFormat::NV12, ColorRange::Full, &bytes, (2, 2));
+        let gray = |v| [v, v, v, 255];
+        assert_eq!(texels, [gray(16), gray(235), gray(235), gray(16)]);
+    }
+
+    #[test]
+    fn replicates_chroma_of_odd_sized_nv12() {
+        // Two rows of two chroma pairs, with full red chroma for the top right pixels
+        let mut bytes = vec![0; 9];
+        bytes.extend_from_slice(&[128, 128, 128, 255, 128, 128, 128, 128]);
+        let texels = decode(PlanarYuvFormat::NV12, ColorRange::Full, &bytes, (3, 3));
+        #[rustfmt::skip]
+        let expected = [
+            BLACK, BLACK, RED,
+            BLACK, BLACK, RED,
+            BLACK, BLACK, BLACK,
+        ];
+        assert_eq!(texels, expected);
+
+        let mut bytes = vec![235; 9];
+        bytes.extend_from_slice(&[128; 8]);
+        let texels = decode(PlanarYuvFormat::NV12, ColorRange::Limited, &bytes, (3, 3));
+        assert_eq!(texels, [WHITE; 9]);
+    }
+
+    #[test]
+    fn decodes_nv11() {
+        // Chroma covers four pixels, so the fifth pixel has its own pair
+        let bytes = [0, 0, 0, 0, 0, 128, 128, 128, 255];
+        let texels = decode(PlanarYuvFormat::NV11, ColorRange::Full, &bytes, (5, 1));
+        assert_eq!(texels, [BLACK, BLACK, BLACK, BLACK, RED]);
+    }
+
+    #[test]
+    fn decodes_p010_high_bits() {
+        // 10-bit limited white and neutral chroma, with noise in the unused low bits
+        let mut samples = vec![(940 << 6) | 0x3F; 9];
+        samples.extend_from_slice(&[(512 << 6) | 0x15; 8]);
+        let texels = decode(
+            PlanarYuvFormat::P010,
+            ColorRange::Limited,
+            &words(&samples),
+            (3, 3),
+        );
+        assert_eq!(texels, [WHITE; 9]);
+
+        let samples = [64 << 6, 1023 << 6, 64 << 6, 64 << 6, 512 << 6, 512 << 6];
+        let texels = decode(
+            PlanarYuvFormat::P010,
+            ColorRange::Limited,
+            &words(&samples),
+            (2, 2),
+        );
+        assert_eq!(texels, [BLACK, WHITE, BLACK, BLACK]);
+    }
+
+    #[test]
+    fn decodes_p016_ranges() {
+        let samples = [16 << 8, 235 << 8, 235 << 8, 16 << 8, 1 << 15, 1 << 15];
+        let texels = decode(
+            PlanarYuvFormat::P016,
+            ColorRange::Limited,
+            &words(&samples),
+            (2, 2),
+        );
+        assert_eq!(texels, [BLACK, WHITE, WHITE, BLACK]);
+
+        let samples = [0, 65535, 65535, 0, 1 << 15, 1 << 15];
+        let texels = decode(
+            PlanarYuvFormat::P016,
+            ColorRange::Full,
+            &words(&samples),
+            (2, 2),
+        );
+        assert_eq!(texels, [BLACK, WHITE, WHITE, BLACK]);
+    }
+}
@@ -0,0 +1,299 @@
+use crate::{DecodedBlock, Decoder, DecoderResult, SurfaceDescriptor};
+
+/// How texels are combined when sampling between texel centers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Filter {
+    /// Returns the texel containing the sample position.
+    #[default]
+    Point,
+    /// Interpolates between the four texels nearest to the sample position.
+    Bilinear,
+}
+
+/// How texel coordinates outside of the surface are mapped back into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AddressMode {
+    /// Repeats the surface.
+    #[default]
+    Wrap,
+    /// Uses the nearest edge texel.
+    Clamp,
+    /// Repeats the surface, flipping every other repetition.
+    Mirror,
+}
+
+impl AddressMode {
+    /// Maps a texel coordinate into `[0, size)`. `size` must not be 0.
+    fn apply(self, coord: isize, size: usize) -> usize {
+        let size = size as isize;
+        match self {
+            AddressMode::Wrap => coord.rem_euclid(size) as usize,
+            AddressMode::Clamp => coord.clamp(0, size - 1) as usize,
+            AddressMode::Mirror => {
+                let coord = coord.rem_euclid(2 * size);
+                if coord < size {
+                    coord as usize
+                } else {
+                    (2 * size - 1 - coord) as usize
+                }
+            }
+        }
+    }
+}
+
+/// Samples a surface through [`Decoder::decode_at_pixel`] without decoding it whole.
+///
+/// Sample positions are normalized, with `(0, 0)` at the top left corner of the surface and
+/// `(1, 1)` at the bottom right, and texel centers at half texel offsets. Only the first depth
+/// slice is sampled. Recently decoded blocks are kept in a small cache, so neighbouring samples
+/// rarely decode a block twice.
+///
+/// Samples use [`Filter::Point`] and [`AddressMode::Wrap`] by default, and return texels as RGBA
+/// floats as in [`DecodedBlock::texel_f32`].
+pub struct Sampler<'a> {
+    decoder: &'a dyn Decoder,
+    bytes: &'a [u8],
+    surface: SurfaceDescriptor,
+    filter: Filter,
+    address_u: AddressMode,
+    address_v: AddressMode,
+    /// Recently decoded blocks, searched linearly by [`DecodedBlock::pixel_f32`] on every fetch.
+    cache: Vec<DecodedBlock>,
+    cache_size: usize,
+    /// Index of the next cache entry to replace once the cache is full.
+    next_evicted: usize,
+}
+
+impl<'a> Sampler<'a> {
+    pub fn new(
+        decoder: &'a dyn Decoder,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> Sampler<'a> {
+        Sampler {
+            decoder,
+            bytes,
+            surface,
+            filter: Filter::default(),
+            address_u: AddressMode::default(),
+            address_v: AddressMode::default(),
+            cache: Vec::new(),
+            cache_size: 16,
+            next_evicted: 0,
+        }
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Sampler<'a> {
+        self.filter = filter;
+        self
+    }
+
+    /// Sets the address mode of both the horizontal and vertical axes.
+    pub fn address_mode(self, mode: AddressMode) -> Sampler<'a> {
+        self.address_modes(mode, mode)
+    }
+
+    /// Sets the address modes of the horizontal and vertical axes separately.
+    pub fn address_modes(mut self, u: AddressMode, v: AddressMode) -> Sampler<'a> {
+        self.address_u = u;
+        self.address_v = v;
+        self
+    }
+
+    /// Sets the number of decoded blocks kept in the cache, 16 by default. A size of 0 disables
+    /// the cache.
+    ///
+    /// Once the cache is full, the oldest block is replaced. Every fetch scans the whole cache, so
+    /// large sizes make cache hits slower.
+    pub fn cache_size(mut self, cache_size: usize) -> Sampler<'a> {
+        self.cache_size = cache_size;
+        self.cache.truncate(cache_size);
+        self.next_evicted = 0;
+        self
+    }
+
+    pub fn surface(&self) -> SurfaceDescriptor {
+        self.surface
+    }
+
+    /// Samples the surface at normalized coordinates `(u, v)`.
+    pub fn sample(&mut self, u: f32, v: f32) -> DecoderResult<[f32; 4]> {
+        let x = u * self.surface.width as f32;
+        let y = v * self.surface.height as f32;
+        match self.filter {
+            Filter::Point => self.fetch(x.floor() as isize, y.floor() as isize),
+            Filter::Bilinear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as isize, y0 as isize);
+
+                let top_left = self.fetch(x0, y0)?;
+                let top_right = self.fetch(x0 + 1, y0)?;
+                let bottom_left = self.fetch(x0, y0 + 1)?;
+                let bottom_right = self.fetch(x0 + 1, y0 + 1)?;
+                let mut texel = [0.0; 4];
+                for (c, out) in texel.iter_mut().enumerate() {
+                    let top = top_left[c] + (top_right[c] - top_left[c]) * tx;
+                    let bottom = bottom_left[c] + (bottom_right[c] - bottom_left[c]) * tx;
+                    *out = top + (bottom - top) * ty;
+                }
+                Ok(texel)
+            }
+        }
+    }
+
+    /// Returns the texel at texel coordinates `(x, y)`, after applying the address modes.
+    pub fn fetch(&mut self, x: isize, y: isize) -> DecoderResult<[f32; 4]> {
+        let (width, height) = (self.surface.width.max(1), self.surface.height.max(1));
+        let x = self.address_u.apply(x, width);
+        let y = self.address_v.apply(y, height);
+
+        if let Some(texel) = self.cache.iter().find_map(|block| block.pixel_f32(x, y)) {
+            return Ok(texel);
+        }
+        let block = self
+            .decoder
+            .decode_at_pixel(self.bytes, self.surface, (x, y))?;
+        let texel = block.pixel_f32(x, y).unwrap_or_default();
+        if self.cache.len() < self.cache_size {
+            self.cache.push(block);
+        } else if self.cache_size > 0 {
+            self.cache[self.next_evicted] = block;
+            self.next_evicted = (self.next_evicted + 1) % self.cache_size;
+        }
+        Ok(texel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Blocks, UncompressedDecoder};
+    use ddsfmt::{ChannelFormat, UncompressedFormat};
+    use std::cell::Cell;
+
+    /// An R8 decoder counting the blocks it decodes one at a time.
+    struct CountingDecoder {
+        inner: UncompressedDecoder,
+        decoded: Cell<usize>,
+    }
+
+    impl CountingDecoder {
+        fn new() -> CountingDecoder {
+            let format = UncompressedFormat::UnsignedNormalized(ChannelFormat::R(8));
+            CountingDecoder {
+                inner: UncompressedDecoder::new(format).unwrap(),
+                decoded: Cell::new(0),
+            }
+        }
+    }
+
+    impl Decoder for CountingDecoder {
+        fn decode_to(
+            &self,
+            bytes: &[u8],
+            surface: SurfaceDescriptor,
+            buffer: &mut Vec<u8>,
+        ) -> DecoderResult<()> {
+            self.inner.decode_to(bytes, surface, buffer)
+        }
+
+        fn decode_at_pixel(
+            &self,
+            bytes: &[u8],
+            surface: SurfaceDescriptor,
+            coords: (usize, usize),
+        ) -> DecoderResult<DecodedBlock> {
+            self.decoded.set(self.decoded.get() + 1);
+            self.inner.decode_at_pixel(bytes, surface, coords)
+        }
+
+        fn blocks<'a>(
+            &'a self,
+            bytes: &'a [u8],
+            surface: SurfaceDescriptor,
+        ) -> DecoderResult<Blocks<'a>> {
+            self.inner.blocks(bytes, surface)
+        }
+    }
+
+    fn red(sampler: &mut Sampler, x: isize) -> f32 {
+        sampler.fetch(x, 0).unwrap()[0]
+    }
+
+    #[test]
+    fn interpolates_bilinear_samples() {
+        let decoder = CountingDecoder::new();
+        let bytes = [0, 255];
+        let surface = SurfaceDescriptor::new(2, 1);
+        let mut sampler = Sampler::new(&decoder, &bytes, surface)
+            .filter(Filter::Bilinear)
+            .address_mode(AddressMode::Clamp);
+        assert_eq!(sampler.sample(0.25, 0.5).unwrap(), [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(sampler.sample(0.5, 0.5).unwrap(), [0.5, 0.0, 0.0, 1.0]);
+        assert_eq!(sampler.sample(0.625, 0.5).unwrap(), [0.75, 0.0, 0.0, 1.0]);
+        assert_eq!(sampler.sample(0.0, 0.0).unwrap(), [0.0, 0.0, 0.0, 1.0]);
+
+        // Wrapping blends the left edge with the right column
+        let mut sampler = sampler.address_mode(AddressMode::Wrap);
+        assert_eq!(sampler.sample(0.0, 0.5).unwrap(), [0.5, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn wraps_point_samples() {
+        let decoder = CountingDecoder::new();
+        let bytes = [0, 255];
+        let mut sampler = Sampler::new(&decoder, &bytes, SurfaceDescriptor::new(2, 1));
+        assert_eq!(sampler.sample(0.0, 0.0).unwrap()[0], 0.0);
+        assert_eq!(sampler.sample(1.0, 0.0).unwrap()[0], 0.0);
+        assert_eq!(sampler.sample(-0.25, 0.0).unwrap()[0], 1.0);
+        assert_eq!(red(&mut sampler, -1), 1.0);
+        assert_eq!(red(&mut sampler, 5), 1.0);
+    }
+
+    #[test]
+    fn mirrors_and_clamps_texel_coordinates() {
+        let decoder = CountingDecoder::new();
+        let bytes = [0, 51, 255];
+        let surface = SurfaceDescriptor::new(3, 1);
+        let mut sampler = Sampler::new(&decoder, &bytes, surface).address_mode(AddressMode::Mirror);
+        assert_eq!(red(&mut sampler, -1), 0.0);
+        assert_eq!(red(&mut sampler, -2), 0.2);
+        assert_eq!(red(&mut sampler, 3), 1.0);
+        assert_eq!(red(&mut sampler, 4), 0.2);
+        assert_eq!(red(&mut sampler, 6), 0.0);
+
+        let mut sampler = sampler.address_mode(AddressMode::Clamp);
+        assert_eq!(red(&mut sampler, -1), 0.0);
+        assert_eq!(red(&mut sampler, 3), 1.0);
+    }
+
+    #[test]
+    fn evicts_oldest_cached_block() {
+        let decoder = CountingDecoder::new();
+        let bytes: Vec<u8> = (0..32).collect();
+        let mut sampler = Sampler::new(&decoder, &bytes, SurfaceDescriptor::new(32, 1));
+        for x in 0..16 {
+            red(&mut sampler, x);
+        }
+        assert_eq!(decoder.decoded.get(), 16);
+        for x in 0..16 {
+            red(&mut sampler, x);
+        }
+        assert_eq!(decoder.decoded.get(), 16);
+
+        // The 17th block replaces the first one only
+        red(&mut sampler, 16);
+        assert_eq!(decoder.decoded.get(), 17);
+        red(&mut sampler, 1);
+        assert_eq!(decoder.decoded.get(), 17);
+        red(&mut sampler, 0);
+        assert_eq!(decoder.decoded.get(), 18);
+
+        let mut sampler = sampler.cache_size(0);
+        red(&mut sampler, 0);
+        red(&mut sampler, 0);
+        assert_eq!(decoder.decoded.get(), 20);
+    }
+}
@@ -0,0 +1,517 @@
+use crate::{
+    decode_block_at, decode_block_surface,
+    float::{f16_to_f32, unsigned_float_to_f32},
+    BlockLayout, Blocks, ChannelType, DecodedBlock, Decoder, DecoderError, DecoderResult,
+    SurfaceDescriptor, TexelFormat,
+};
+use ddsfmt::{ChannelFormat, TextureFormat, UncompressedFormat};
+
+/// How the bits of each channel are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Interpretation {
+    Float,
+    Unsigned,
+    Signed,
+    UnsignedInt,
+    SignedInt,
+}
+
+/// A channel of a pixel, stored in `bits` bits from `shift` upwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Field {
+    /// Index of the RGBA channel the field is written to, or `None` for unused bits.
+    channel: Option<usize>,
+    shift: u32,
+    bits: u32,
+}
+
+/// The fields making up a pixel and how pixels are packed into blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PixelLayout {
+    fields: [Field; 4],
+    field_count: usize,
+    /// Whether the red field holds luminance, replicated to green and blue.
+    luminance: bool,
+    block: BlockLayout,
+}
+
+impl PixelLayout {
+    fn new(fields: &[Field], luminance: bool, pixel_bits: u32) -> Option<PixelLayout> {
+        let block = match pixel_bits {
+            1 | 2 | 4 => BlockLayout::new(8 / pixel_bits as usize, 1, 1),
+            bits if bits % 8 == 0 && bits <= 128 => BlockLayout::new(1, 1, bits as usize / 8),
+            _ => return None,
+        };
+        let mut layout = PixelLayout {
+            fields: [Field {
+                channel: None,
+                shift: 0,
+                bits: 0,
+            }; 4],
+            field_count: fields.len(),
+            luminance,
+            block,
+        };
+        layout.fields[..fields.len()].copy_from_slice(fields);
+        Some(layout)
+    }
+}
+
+/// Lays out the channels of a `ChannelFormat` other than the bit masks from the lowest bits up.
+fn channel_layout(channels: ChannelFormat, interpretation: Interpretation) -> Option<PixelLayout> {
+    let (r, g, b, a) = (Some(0), Some(1), Some(2), Some(3));
+    let (order, bits): ([Option<usize>; 4], [usize; 4]) = match channels {
+        ChannelFormat::RGBA(r0, g0, b0, a0) => ([r, g, b, a], [r0, g0, b0, a0]),
+        ChannelFormat::BGRA(b0, g0, r0, a0) => ([b, g, r, a], [b0, g0, r0, a0]),
+        ChannelFormat::BGRUnused(b0, g0, r0, x0) => ([b, g, r, None], [b0, g0, r0, x0]),
+        ChannelFormat::RGB(r0, g0, b0) => ([r, g, b, None], [r0, g0, b0, 0]),
+        ChannelFormat::BGR(b0, g0, r0) => ([b, g, r, None], [b0, g0, r0, 0]),
+        ChannelFormat::RG(r0, g0) => ([r, g, None, None], [r0, g0, 0, 0]),
+        ChannelFormat::R(r0) => ([r, None, None, None], [r0, 0, 0, 0]),
+        ChannelFormat::A(a0) => ([a, None, None, None], [a0, 0, 0, 0]),
+        ChannelFormat::BitMask(..) | ChannelFormat::LuminanceBitMask(..) => return None,
+    };
+
+    let mut fields = Vec::with_capacity(4);
+    let mut shift = 0;
+    for (&channel, &bits) in order.iter().zip(bits.iter()).filter(|(_, &b)| b > 0) {
+        let bits = bits as u32;
+        let valid = match interpretation {
+            Interpretation::Float => channel.is_none() || [10, 11, 16, 32].contains(&bits),
+            _ => bits <= 32,
+        };
+        if !valid {
+            return None;
+        }
+        fields.push(Field {
+            channel,
+            shift,
+            bits,
+        });
+        shift += bits;
+    }
+    PixelLayout::new(&fields, false, shift)
+}
+
+/// Lays out the channels of a legacy DDS pixel format from its red, green, blue and alpha masks.
+///
+/// With `luminance`, the red mask holds luminance. Masks must be contiguous and fit in the pixel.
+fn bit_mask_layout(pixel_bits: u32, masks: [u32; 4], luminance: bool) -> Option<PixelLayout> {
+    if ![8, 16, 24, 32].contains(&pixel_bits) {
+        return None;
+    }
+    let fields: Vec<Field> = masks
+        .iter()
+        .enumerate()
+        .filter(|(_, &mask)| mask != 0)
+        .map(|(channel, &mask)| Field {
+            channel: Some(channel),
+            shift: mask.trailing_zeros(),
+            bits: mask.count_ones(),
+        })
+        .collect();
+    if fields.iter().any(|field| {
+        field.shift + field.bits > pixel_bits
+            || field.bits != 32 - masks[field.channel.unwrap()].leading_zeros() - field.shift
+    }) {
+        return None;
+    }
+    PixelLayout::new(&fields, luminance, pixel_bits)
+}
+
+/// Decoder for uncompressed surfaces described by a [`UncompressedFormat`], producing RGBA texels.
+///
+/// Channels are listed from the least significant bits of each little-endian pixel, so `RGBA`
+/// stores red in the first byte and `BGR(5, 6, 5)` stores blue in the lowest 5 bits. Pixels of
+/// fewer than 8 bits, such as `R(1)`, are packed from the most significant bit of each byte.
+///
+/// `BitMask` and `LuminanceBitMask` formats from legacy DDS headers extract each channel with its
+/// mask, expanding masks of any width to the full range. Luminance is replicated to red, green and
+/// blue.
+///
+/// Normalized and typeless formats are scaled over the range of each channel, and sRGB formats
+/// keep their sRGB encoding. `UnsignedInt` and `SignedInt` formats decode to their integer
+/// values, which [`ChannelType::Float32`] keeps while the normalized channel types
+/// saturate them, so any value of 1 or more becomes the maximum. Missing color channels decode as
+/// 0 and missing alpha as opaque. Texels are written as [`ChannelType::Norm8`] by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UncompressedDecoder {
+    format: UncompressedFormat,
+    channel_type: ChannelType,
+    interpretation: Interpretation,
+    layout: PixelLayout,
+}
+
+impl UncompressedDecoder {
+    /// Creates a decoder for `format`.
+    ///
+    /// Fails with [`DecoderError::UnsupportedFormat`] for special formats, float channels other
+    /// than 10, 11, 16 or 32 bits, channel layouts not filling whole bytes, and bit masks that
+    /// aren't unsigned normalized, aren't contiguous, or whose pixels aren't 8, 16, 24 or 32 bits.
+    pub fn new(format: UncompressedFormat) -> DecoderResult<UncompressedDecoder> {
+        let unsupported = || DecoderError::UnsupportedFormat(TextureFormat::Uncompressed(format));
+        let (interpretation, channels) = match format {
+            UncompressedFormat::Float(channels) => (Interpretation::Float, channels),
+            UncompressedFormat::SignedNormalized(channels) => (Interpretation::Signed, channels),
+            UncompressedFormat::Typeless(channels)
+            | UncompressedFormat::UnsignedNormalized(channels)
+            | UncompressedFormat::UnsignedNormalizedSrgb(channels) => {
+                (Interpretation::Unsigned, channels)
+            }
+            UncompressedFormat::UnsignedInt(channels) => (Interpretation::UnsignedInt, channels),
+            UncompressedFormat::SignedInt(channels) => (Interpretation::SignedInt, channels),
+            UncompressedFormat::Other(_) => return Err(unsupported()),
+        };
+
+        let layout = match channels {
+            ChannelFormat::BitMask(bits, r, g, b, a) => match format {
+                UncompressedFormat::UnsignedNormalized(_) => {
+                    bit_mask_layout(bits, [r, g, b, a], false)
+                }
+                _ => None,
+            },
+            ChannelFormat::LuminanceBitMask(bits, l, a) => match format {
+                UncompressedFormat::UnsignedNormalized(_) => {
+                    bit_mask_layout(bits, [l, 0, 0, a], true)
+                }
+                _ => None,
+            },
+            _ => channel_layout(channels, interpretation),
+        }
+        .ok_or_else(unsupported)?;
+
+        Ok(UncompressedDecoder {
+            format,
+            channel_type: ChannelType::Norm8,
+            interpretation,
+            layout,
+        })
+    }
+
+    /// Sets the storage of each decoded channel.
+    pub fn channel_type(mut self, channel_type: ChannelType) -> UncompressedDecoder {
+        self.channel_type = channel_type;
+        self
+    }
+
+    pub fn format(&self) -> UncompressedFormat {
+        self.format
+    }
+
+    fn signed(&self) -> bool {
+        matches!(
+            self.interpretation,
+            Interpretation::Signed | Interpretation::SignedInt
+        )
+    }
+
+    fn texel_format(&self) -> TexelFormat {
+        TexelFormat::new(4, self.channel_type, self.signed())
+    }
+
+    fn decode_block(&self, block: &[u8], texels: &mut [u8]) {
+        if self.layout.block.width > 1 {
+            // Sub-byte pixels, packed from the most significant bit
+            let bits = 8 / self.layout.block.width;
+            let texel_size = self.texel_format().size();
+            for (i, texel) in texels.chunks_exact_mut(texel_size).enumerate() {
+                let shift = 8 - bits * (i + 1);
+                self.decode_pixel((block[0] >> shift) as u128, texel);
+            }
+        } else {
+            let mut pixel = [0; 16];
+            pixel[..block.len()].copy_from_slice(block);
+            self.decode_pixel(u128::from_le_bytes(pixel), texels);
+        }
+    }
+
+    fn decode_pixel(&self, pixel: u128, texel: &mut [u8]) {
+        let mut values = [0.0, 0.0, 0.0, 1.0];
+        for field in &self.layout.fields[..self.layout.field_count] {
+            if let Some(channel) = field.channel {
+                let raw = ((pixel >> field.shift) as u64 & ((1 << field.bits) - 1)) as u32;
+                values[channel] = self.channel_value(raw, field.bits);
+            }
+        }
+        if self.layout.luminance {
+            values[1] = values[0];
+            values[2] = values[0];
+        }
+        let size = self.channel_type.size();
+        for (value, out) in values.iter().zip(texel.chunks_exact_mut(size)) {
+            self.channel_type.write(*value, self.signed(), out);
+        }
+    }
+
+    fn channel_value(&self, raw: u32, bits: u32) -> f32 {
+        match self.interpretation {
+            Interpretation::Float => match bits {
+                32 => f32::from_bits(raw),
+                16 => f16_to_f32(raw as u16),
+                _ => unsigned_float_to_f32(raw, bits - 5),
+            },
+            Interpretation::Unsigned => (raw as f64 / ((1u64 << bits) - 1) as f64) as f32,
+            Interpretation::Signed => {
+                let max = ((1u64 << (bits - 1)) - 1).max(1) as f64;
+                (sign_extend(raw, bits) as f64 / max).max(-1.0) as f32
+            }
+            Interpretation::UnsignedInt => raw as f32,
+            Interpretation::SignedInt => sign_extend(raw, bits) as f32,
+        }
+    }
+}
+
+/// Sign-extends the lowest `bits` bits of `raw`.
+fn sign_extend(raw: u32, bits: u32) -> i32 {
+    ((raw << (32 - bits)) as i32) >> (32 - bits)
+}
+
+impl Decoder for UncompressedDecoder {
+    fn decode_to(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        buffer: &mut Vec<u8>,
+    ) -> DecoderResult<()> {
+        decode_block_surface(
+            bytes,
+            surface,
+            self.layout.block,
+            self.texel_format().size(),
+            buffer,
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn decode_at_pixel(
+        &self,
+        bytes: &[u8],
+        surface: SurfaceDescriptor,
+        coords: (usize, usize),
+    ) -> DecoderResult<DecodedBlock> {
+        decode_block_at(
+            bytes,
+            surface,
+            coords,
+            self.layout.block,
+            self.texel_format(),
+            |block, texels| self.decode_block(block, texels),
+        )
+    }
+
+    fn blocks<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        surface: SurfaceDescriptor,
+    ) -> DecoderResult<Blocks<'a>> {
+        Blocks::new(self, bytes, surface, self.layout.block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::packed_float::{decode_packed_float, PackedFloatFormat};
+    use ddsfmt::formats;
+
+    fn decoder(format: TextureFormat, channel_type: ChannelType) -> UncompressedDecoder {
+        match format {
+            TextureFormat::Uncompressed(format) => UncompressedDecoder::new(format)
+                .unwrap()
+                .channel_type(channel_type),
+            _ => panic!("{:?} is not uncompressed", format),
+        }
+    }
+
+    fn decode(format: TextureFormat, bytes: &[u8], width: usize) -> Vec<u8> {
+        decoder(format, ChannelType::Norm8)
+            .decode(bytes, SurfaceDescriptor::new(width, 1))
+            .unwrap()
+    }
+
+    fn decode_f32(format: TextureFormat, bytes: &[u8]) -> Vec<f32> {
+        decoder(format, ChannelType::Float32)
+            .decode(bytes, SurfaceDescriptor::new(1, 1))
+            .unwrap()
+            .chunks_exact(4)
+            .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn lists_channels_from_least_significant_bits() {
+        assert_eq!(
+            decode(formats::R8G8B8A8_UNORM, &[1, 2, 3, 4], 1),
+            [1, 2, 3, 4]
+        );
+        assert_eq!(
+            decode(formats::B8G8R8A8_UNORM, &[1, 2, 3, 4], 1),
+            [3, 2, 1, 4]
+        );
+        assert_eq!(
+            decode(formats::B8G8R8X8_UNORM, &[1, 2, 3, 4], 1),
+            [3, 2, 1, 255]
+        );
+    }
+
+    #[test]
+    fn packs_sub_byte_pixels_from_most_significant_bit() {
+        let texels = decode(formats::R1_UNORM, &[0b1100_0001], 8);
+        let red: Vec<u8> = texels.chunks_exact(4).map(|texel| texel[0]).collect();
+        assert_eq!(red, [255, 255, 0, 0, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn decodes_packed_channels() {
+        let b5g6r5 = |pixel: u16| decode(formats::B5G6R5_UNORM, &pixel.to_le_bytes(), 1);
+        assert_eq!(b5g6r5(0x001F), [0, 0, 255, 255]);
+        assert_eq!(b5g6r5(0x07E0), [0, 255, 0, 255]);
+        assert_eq!(b5g6r5(0x8000), [132, 0, 0, 255]);
+
+        let texels = decode(formats::B4G4R4A4_UNORM, &0xF80Fu16.to_le_bytes(), 1);
+        assert_eq!(texels, [136, 0, 255, 255]);
+
+        let pixel: u32 = 1023 | 512 << 20 | 1 << 30;
+        let texels = decode(formats::R10G10B10A2_UNORM, &pixel.to_le_bytes(), 1);
+        assert_eq!(texels, [255, 0, 128, 85]);
+    }
+
+    #[test]
+    fn clamps_snorm_minimum() {
+        assert_eq!(
+            decode_f32(formats::R8_SNORM, &[0x80]),
+            [-1.0, 0.0, 0.0, 1.0]
+        );
+        assert_eq!(
+            decode_f32(formats::R8_SNORM, &[0x81]),
+            [-1.0, 0.0, 0.0, 1.0]
+        );
+        assert_eq!(decode_f32(formats::R8_SNORM, &[0x7F]), [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(decode(formats::R8_SNORM, &[0x80], 1), [0x81, 0, 0, 0x7F]);
+    }
+
+    #[test]
+    fn keeps_srgb_encoding() {
+        let texels = decode(formats::R8G8B8A8_UNORM_SRGB, &[10, 128, 200, 255], 1);
+        assert_eq!(texels, [10, 128, 200, 255]);
+    }
+
+    #[test]
+    fn decodes_missing_alpha_as_opaque() {
+        assert_eq!(decode(formats::R8G8_UNORM, &[1, 2], 1), [1, 2, 0, 255]);
+        assert_eq!(
+            decode_f32(formats::R8G8_UNORM, &[0, 255]),
+            [0.0, 1.0, 0.0, 1.0]
+        );
+    }
+
+    #[test]
+    fn decodes_integer_values() {
+        let texels = decode_f32(formats::R32_UINT, &70000u32.to_le_bytes());
+        assert_eq!(texels, [70000.0, 0.0, 0.0, 1.0]);
+        assert_eq!(
+            decode(formats::R32_UINT, &70000u32.to_le_bytes(), 1),
+            [255, 0, 0, 255]
+        );
+
+        let bytes = [0, 1, 200, 255];
+        assert_eq!(
+            decode_f32(formats::R8G8B8A8_UINT, &bytes),
+            [0.0, 1.0, 200.0, 255.0]
+        );
+        let texels = decoder(formats::R8G8B8A8_UINT, ChannelType::Norm16)
+            .decode(&bytes, SurfaceDescriptor::new(1, 1))
+            .unwrap();
+        let expected: Vec<u8> = [0u16, 65535, 65535, 65535]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect();
+        assert_eq!(texels, expected);
+    }
+
+    #[test]
+    fn decodes_signed_integer_values() {
+        let bytes: Vec<u8> = [-300i16, 5].iter().flat_map(|v| v.to_le_bytes()).collect();
+        assert_eq!(
+            decode_f32(formats::R16G16_SINT, &bytes),
+            [-300.0, 5.0, 0.0, 1.0]
+        );
+        assert_eq!(
+            decode(formats::R16G16_SINT, &bytes, 1),
+            [0x81, 0x7F, 0, 0x7F]
+        );
+    }
+
+    #[test]
+    fn matches_packed_float_decoder() {
+        let mut state: u32 = 7;
+        for _ in 0..256 {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let texel = decode_f32(formats::R11G11B10_FLOAT, &state.to_le_bytes());
+            let expected = decode_packed_float(PackedFloatFormat::R11G11B10Float, state);
+            let bits = |values: &[f32]| values.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+            assert_eq!(bits(&texel), bits(&expected), "{:#010x}", state);
+        }
+    }
+
+    fn decode_bit_mask(channels: ChannelFormat, bytes: &[u8]) -> Vec<u8> {
+        let format = UncompressedFormat::UnsignedNormalized(channels);
+        UncompressedDecoder::new(format)
+            .unwrap()
+            .decode(bytes, SurfaceDescriptor::new(1, 1))
+            .unwrap()
+    }
+
+    #[test]
+    fn decodes_rgb_bit_masks() {
+        // X1R5G5B5 ignores its unused top bit
+        let x1r5g5b5 = ChannelFormat::BitMask(16, 0x7C00, 0x03E0, 0x001F, 0);
+        assert_eq!(decode_bit_mask(x1r5g5b5, &[0x1F, 0x80]), [0, 0, 255, 255]);
+        assert_eq!(decode_bit_mask(x1r5g5b5, &[0x00, 0x7C]), [255, 0, 0, 255]);
+
+        let r8g8b8 = ChannelFormat::BitMask(24, 0xFF0000, 0x00FF00, 0x0000FF, 0);
+        assert_eq!(decode_bit_mask(r8g8b8, &[1, 2, 3]), [3, 2, 1, 255]);
+
+        // Without the luminance flag a lone red mask stays red
+        let r8 = ChannelFormat::BitMask(8, 0xFF, 0, 0, 0);
+        assert_eq!(decode_bit_mask(r8, &[77]), [77, 0, 0, 255]);
+    }
+
+    #[test]
+    fn decodes_alpha_bit_mask() {
+        let a8 = ChannelFormat::BitMask(8, 0, 0, 0, 0xFF);
+        assert_eq!(decode_bit_mask(a8, &[77]), [0, 0, 0, 77]);
+    }
+
+    #[test]
+    fn decodes_luminance_bit_masks() {
+        let a4l4 = ChannelFormat::LuminanceBitMask(8, 0x0F, 0xF0);
+        assert_eq!(decode_bit_mask(a4l4, &[0x5A]), [170, 170, 170, 85]);
+
+        let a8l8 = ChannelFormat::LuminanceBitMask(16, 0x00FF, 0xFF00);
+        assert_eq!(decode_bit_mask(a8l8, &[0x40, 0x80]), [64, 64, 64, 128]);
+
+        let l16 = ChannelFormat::LuminanceBitMask(16, 0xFFFF, 0);
+        assert_eq!(decode_bit_mask(l16, &[0xFF, 0xFF]), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn rejects_unsupported_bit_masks() {
+        let unsupported = |channels| {
+            let format = UncompressedFormat::UnsignedNormalized(channels);
+            assert!(matches!(
+                UncompressedDecoder::new(format),
+                Err(DecoderError::UnsupportedFormat(TextureFormat::Uncompressed(f))) if f == format
+            ));
+        };
+        // Non-contiguous masks
+        unsupported(ChannelFormat::BitMask(16, 0x0F0F, 0, 0, 0));
+        unsupported(ChannelFormat::LuminanceBitMask(16, 0x00FF, 0xF00F));
+        // Masks outside of the pixel
+        unsupported(ChannelFormat::BitMask(
+            24, 0xFF000000, 0x00FF00, 0x0000FF, 0,
+        ));
+        // Pixel sizes other than whole bytes up to 32 bits
+        unsupported(ChannelFormat::BitMask(12, 0xF00, 0x0F0, 0x00F, 0));
+    }
+}
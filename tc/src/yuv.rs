@@ -0,0 +1,68 @@
+//! Conversion from Y'CbCr samples to RGB.
+
+/// Color matrix used to convert Y'CbCr samples to RGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorMatrix {
+    /// ITU-R BT.601, used by standard definition video.
+    #[default]
+    Bt601,
+    /// ITU-R BT.709, used by high definition video.
+    Bt709,
+    /// ITU-R BT.2020, used by ultra high definition and HDR video.
+    Bt2020,
+}
+
+impl ColorMatrix {
+    /// The luma coefficients of red and blue, `(Kr, Kb)`.
+    fn coefficients(self) -> (f32, f32) {
+        match self {
+            ColorMatrix::Bt601 => (0.299, 0.114),
+            ColorMatrix::Bt709 => (0.2126, 0.0722),
+            ColorMatrix::Bt2020 => (0.2627, 0.0593),
+        }
+    }
+}
+
+/// Range of the encoded Y'CbCr samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorRange {
+    /// Studio range, where 8-bit luma spans 16-235 and chroma spans 16-240.
+    #[default]
+    Limited,
+    /// Full range, where samples span every representable value.
+    Full,
+}
+
+/// Converts Y'CbCr samples of the given bit depth to RGB values clamped to `[0, 1]`.
+///
+/// Limited range scales the 8-bit studio range to higher bit depths, as done by 10 and 16-bit
+/// video formats.
+pub(crate) fn yuv_to_rgb(
+    (y, u, v): (u32, u32, u32),
+    bits: u32,
+    matrix: ColorMatrix,
+    range: ColorRange,
+) -> [f32; 3] {
+    let (y, u, v) = (y as f32, u as f32, v as f32);
+    let center = (1u32 << (bits - 1)) as f32;
+    let (y, cb, cr) = match range {
+        ColorRange::Limited => {
+            let scale = (1u32 << (bits - 8)) as f32;
+            (
+                (y - 16.0 * scale) / (219.0 * scale),
+                (u - center) / (224.0 * scale),
+                (v - center) / (224.0 * scale),
+            )
+        }
+        ColorRange::Full => {
+            let max = ((1u64 << bits) - 1) as f32;
+            (y / max, (u - center) / max, (v - center) / max)
+        }
+    };
+
+    let (kr, kb) = matrix.coefficients();
+    let r = y + 2.0 * (1.0 - kr) * cr;
+    let b = y + 2.0 * (1.0 - kb) * cb;
+    let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+    [r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0)]
+}